@@ -1,13 +1,45 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use crate::config::Config;
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(name = "Waylock", version, about = "Wayland session lock")]
 pub struct Args {
     #[command(flatten)]
     pub config: Config,
 
-    #[arg(short = 'd', long)]
+    #[arg(short = 'd', long, conflicts_with = "preview")]
     pub daemonize: bool,
+
+    /// Show the lock screen in a regular window without locking the session
+    #[arg(long)]
+    pub preview: bool,
+
+    /// Monitor to take size and screenshot from in preview mode
+    #[arg(long, requires = "preview", default_value_t = 0)]
+    pub monitor: u32,
+
+    /// Password accepted in preview mode
+    #[arg(long, requires = "preview", default_value = "preview")]
+    pub preview_password: String,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Manage pre-rendered background cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone, Copy)]
+pub enum CacheAction {
+    /// Render backgrounds with effects for connected monitors
+    Warm,
+    /// Remove all cached backgrounds
+    Clear,
 }
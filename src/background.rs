@@ -0,0 +1,324 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use gtk::{ContentFit, gdk, glib, graphene, gsk, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{effects::Effect, widgets::slideshow};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("invalid color {0:?}")]
+    Color(String),
+
+    #[error("gradient has no colors")]
+    NoColors,
+
+    #[error("{0:?} does not exist")]
+    NotFound(PathBuf),
+
+    #[error("failed to load {0:?}: {1}")]
+    Load(PathBuf, glib::Error),
+
+    #[error("no images in {0:?}")]
+    EmptySlideshow(PathBuf),
+
+    #[error("no screenshot of this monitor")]
+    NoScreenshot,
+
+    #[error("nothing was drawn on a {0}x{1} area")]
+    Render(f32, f32),
+
+    #[error("{0} background is not supported by this build")]
+    Unsupported(&'static str),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fit {
+    Fill,
+    Contain,
+    #[default]
+    Cover,
+    ScaleDown,
+}
+
+impl From<Fit> for ContentFit {
+    fn from(fit: Fit) -> Self {
+        match fit {
+            Fit::Fill => Self::Fill,
+            Fit::Contain => Self::Contain,
+            Fit::Cover => Self::Cover,
+            Fit::ScaleDown => Self::ScaleDown,
+        }
+    }
+}
+
+/// What is drawn behind the lock screen, unknown keys are an error
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Background {
+    /// Solid CSS color
+    Color { color: String },
+    /// Linear gradient, `angle` in degrees like in CSS, 180 is top to bottom
+    Linear {
+        colors: Vec<String>,
+        #[serde(default = "default::angle")]
+        angle: f32,
+    },
+    /// Radial gradient from the center to the corners
+    Radial { colors: Vec<String> },
+    /// Image, with `effects` it is fitted to the monitor and cached after effects
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        fit: Fit,
+        #[serde(default)]
+        effects: Option<Vec<Effect>>,
+    },
+    /// Video played at normal speed, GTK media streams have no playback rate
+    /// so a `speed` key is rejected
+    Video {
+        path: PathBuf,
+        #[serde(default = "default::enabled", rename = "loop")]
+        looped: bool,
+        #[serde(default = "default::enabled")]
+        muted: bool,
+    },
+    /// Images of a directory or glob pattern, see `slideshow-*` options
+    Slideshow { path: PathBuf },
+    /// Screenshot of the monitor, `effects` override global `effects`
+    Screenshot {
+        #[serde(default)]
+        effects: Option<Vec<Effect>>,
+    },
+}
+
+mod default {
+    pub const fn angle() -> f32 {
+        180.0
+    }
+
+    pub const fn enabled() -> bool {
+        true
+    }
+}
+
+impl Background {
+    /// Short form used on command line and as plain string in config:
+    /// `screenshot`, a directory or glob, an image or video path, or a CSS color
+    pub fn parse(s: &str) -> Self {
+        let path = Path::new(s);
+
+        if s == "screenshot" {
+            Self::Screenshot { effects: None }
+        } else if slideshow::is_slideshow(path) {
+            Self::Slideshow { path: path.into() }
+        } else if !path.exists() && gdk::RGBA::parse(s).is_ok() {
+            Self::Color { color: s.into() }
+        } else if is_video(path) {
+            Self::Video {
+                path: path.into(),
+                looped: true,
+                muted: true,
+            }
+        } else {
+            Self::Image {
+                path: path.into(),
+                fit: Fit::default(),
+                effects: None,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "video")]
+fn is_video(path: &Path) -> bool {
+    infer::get_from_path(path)
+        .ok()
+        .flatten()
+        .is_some_and(|kind| kind.matcher_type() == infer::MatcherType::Video)
+}
+
+#[cfg(not(feature = "video"))]
+fn is_video(_path: &Path) -> bool {
+    false
+}
+
+/// Backgrounds tried in order until one loads
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct BackgroundChain(pub Vec<Background>);
+
+impl std::str::FromStr for BackgroundChain {
+    type Err = std::convert::Infallible;
+
+    /// Comma separated list of short forms
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            s.split(',').map(str::trim).map(Background::parse).collect(),
+        ))
+    }
+}
+
+impl<'de> Deserialize<'de> for BackgroundChain {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Tables are deserialized on their own, untagged enums hide why one does not match
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Item {
+            Short(String),
+            Full(toml::Table),
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Chain {
+            One(Item),
+            Many(Vec<Item>),
+        }
+
+        let items = match Chain::deserialize(deserializer)? {
+            Chain::One(item) => vec![item],
+            Chain::Many(items) => items,
+        };
+
+        items
+            .into_iter()
+            .map(|item| match item {
+                Item::Short(s) => Ok(Background::parse(&s)),
+                Item::Full(table) => Background::deserialize(toml::Value::Table(table))
+                    .map_err(serde::de::Error::custom),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Default chain and per-monitor chains keyed by connector name
+#[derive(Debug, Default, Clone, PartialEq, glib::Boxed)]
+#[boxed_type(name = "WaylockBackgrounds")]
+pub struct Backgrounds {
+    pub default: BackgroundChain,
+    pub monitors: HashMap<String, BackgroundChain>,
+}
+
+impl Backgrounds {
+    pub fn get(&self, monitor: Option<&gdk::Monitor>) -> &[Background] {
+        monitor
+            .and_then(|monitor| monitor.connector())
+            .and_then(|connector| self.monitors.get(connector.as_str()))
+            .unwrap_or(&self.default)
+            .0
+            .as_slice()
+    }
+
+    /// Whether any monitor may need a screenshot
+    pub fn has_screenshot(&self) -> bool {
+        std::iter::once(&self.default)
+            .chain(self.monitors.values())
+            .flat_map(|chain| &chain.0)
+            .any(|background| matches!(background, Background::Screenshot { .. }))
+    }
+}
+
+fn parse_color(color: &str) -> Result<gdk::RGBA, Error> {
+    gdk::RGBA::parse(color).map_err(|_| Error::Color(color.into()))
+}
+
+fn stops(colors: &[String]) -> Result<Vec<gsk::ColorStop>, Error> {
+    let colors = colors
+        .iter()
+        .map(|color| parse_color(color))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match colors.as_slice() {
+        [] => Err(Error::NoColors),
+        [color] => Ok(vec![
+            gsk::ColorStop::new(0.0, *color),
+            gsk::ColorStop::new(1.0, *color),
+        ]),
+        _ => {
+            let last = (colors.len() - 1) as f32;
+            Ok(colors
+                .iter()
+                .enumerate()
+                .map(|(i, color)| gsk::ColorStop::new(i as f32 / last, *color))
+                .collect())
+        }
+    }
+}
+
+/// Paintable of a color or gradient background of `width`x`height`
+pub fn paint(background: &Background, width: f32, height: f32) -> Result<gdk::Paintable, Error> {
+    let snapshot = gtk::Snapshot::new();
+    let bounds = graphene::Rect::new(0.0, 0.0, width, height);
+
+    match background {
+        Background::Color { color } => snapshot.append_color(&parse_color(color)?, &bounds),
+        Background::Linear { colors, angle } => {
+            // Gradient line as in CSS, corners get the first and last colors
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let length = (width * sin).abs() + (height * cos).abs();
+            let (dx, dy) = (sin * length / 2.0, -cos * length / 2.0);
+            let (cx, cy) = (width / 2.0, height / 2.0);
+
+            snapshot.append_linear_gradient(
+                &bounds,
+                &graphene::Point::new(cx - dx, cy - dy),
+                &graphene::Point::new(cx + dx, cy + dy),
+                &stops(colors)?,
+            );
+        }
+        Background::Radial { colors } => snapshot.append_radial_gradient(
+            &bounds,
+            &graphene::Point::new(width / 2.0, height / 2.0),
+            width / std::f32::consts::SQRT_2,
+            height / std::f32::consts::SQRT_2,
+            0.0,
+            1.0,
+            &stops(colors)?,
+        ),
+        _ => unreachable!("{background:?} is not painted"),
+    }
+
+    snapshot
+        .to_paintable(Some(&graphene::Size::new(width, height)))
+        .ok_or(Error::Render(width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(toml: &str) -> Result<BackgroundChain, toml::de::Error> {
+        #[derive(Deserialize)]
+        struct Config {
+            background: BackgroundChain,
+        }
+
+        toml::from_str::<Config>(toml).map(|config| config.background)
+    }
+
+    #[test]
+    fn video() {
+        assert_eq!(
+            chain("background = { type = \"video\", path = \"a.mp4\", loop = false }").unwrap(),
+            BackgroundChain(vec![Background::Video {
+                path: "a.mp4".into(),
+                looped: false,
+                muted: true,
+            }])
+        );
+    }
+
+    #[test]
+    fn video_speed_is_rejected() {
+        let err = chain("background = [{ type = \"video\", path = \"a.mp4\", speed = 2.0 }]")
+            .unwrap_err();
+
+        assert!(err.to_string().contains("unknown field `speed`"), "{err}");
+    }
+}
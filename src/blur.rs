@@ -1,90 +1,312 @@
+use clap::ValueEnum;
+use gtk::{gdk, glib};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+use crate::{config, log};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
 pub enum BlurMethod {
     CPU,
     GPU,
+    /// Use GPU if there is an adapter, otherwise CPU
+    #[default]
+    Auto,
 }
 
-pub fn do_blur(image: &mut blur::Image, method: BlurMethod) -> gtk::gdk::MemoryTexture {
-    let rowstride = (image.width * 4) as usize; // bytes per row (width * 4 bytes for RGBA)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BlurAlgorithm {
+    #[default]
+    Gaussian,
+    Stack,
+    Box,
+}
 
-    match method {
-        BlurMethod::GPU => {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Boxed)]
+#[boxed_type(name = "WaylockBlurParams")]
+pub struct BlurParams {
+    pub method: BlurMethod,
+    pub algorithm: BlurAlgorithm,
+    pub radius: u32,
+    pub passes: u32,
+    /// Image is shrunk by this factor before blurring
+    pub downscale: u32,
+}
+
+impl Default for BlurParams {
+    fn default() -> Self {
+        Self {
+            method: BlurMethod::default(),
+            algorithm: BlurAlgorithm::default(),
+            radius: config::default::BLUR_RADIUS,
+            passes: config::default::BLUR_PASSES,
+            downscale: config::default::BLUR_DOWNSCALE,
+        }
+    }
+}
+
+/// Resolve `Auto` into a concrete method
+pub fn resolve_method(params: &BlurParams) -> BlurMethod {
+    match params.method {
+        BlurMethod::Auto => {
             #[cfg(feature = "gpu")]
-            {
-                use futures::executor::block_on;
-                use image::Rgba;
-                let filters = futures::executor::block_on(blur::Filters::new());
-                let operation = image
-                    .operation(&filters)
-                    .gaussian_blur(16.0)
-                    .gaussian_blur(16.0)
-                    .gaussian_blur(16.0);
-                let result = block_on(operation.execute());
-
-                match image::ImageBuffer::<Rgba<u8>, _>::from_raw(
-                    result.width,
-                    result.height,
-                    result.as_raw(),
-                ) {
-                    Some(buffer) => gtk::gdk::MemoryTexture::new(
-                        buffer.width() as i32,
-                        buffer.height() as i32,
-                        gtk::gdk::MemoryFormat::R8g8b8a8,
-                        &gtk::glib::Bytes::from_owned(bytemuck::cast_vec(buffer.to_vec())),
-                        rowstride,
-                    ),
-                    None => {
-                        do_blur(image, BlurMethod::CPU)
-                    },
+            if params.algorithm == BlurAlgorithm::Gaussian && crate::gpu::available() {
+                return BlurMethod::GPU;
+            }
+
+            BlurMethod::CPU
+        }
+        method => method,
+    }
+}
+
+/// Shrink image by an integer factor averaging each `factor`x`factor` block
+fn downscale(image: &blur::Image, factor: u32) -> blur::Image {
+    let width = (image.width / factor).max(1);
+    let height = (image.height / factor).max(1);
+    let src: &[u8] = bytemuck::cast_slice(&image.pixels);
+    let mut dst = vec![0u8; (width * height * 4) as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u32; 4];
+            let mut count = 0;
+
+            for sy in (y * factor)..((y + 1) * factor).min(image.height) {
+                for sx in (x * factor)..((x + 1) * factor).min(image.width) {
+                    let i = ((sy * image.width + sx) * 4) as usize;
+                    for c in 0..4 {
+                        sum[c] += u32::from(src[i + c]);
+                    }
+                    count += 1;
                 }
             }
+
+            let o = ((y * width + x) * 4) as usize;
+            for c in 0..4 {
+                dst[o + c] = (sum[c] / count.max(1)) as u8;
+            }
+        }
+    }
+
+    blur::Image {
+        width,
+        height,
+        pixels: bytemuck::cast_vec(dst),
+    }
+}
+
+/// Scale image to cover `width`x`height` cropping the center, like `ContentFit::Cover`
+pub fn resize_cover(image: &blur::Image, width: u32, height: u32) -> blur::Image {
+    resize(image, width, height, true)
+}
+
+/// Scale image to exactly `width`x`height` ignoring aspect ratio, like `ContentFit::Fill`
+pub fn resize_fill(image: &blur::Image, width: u32, height: u32) -> blur::Image {
+    resize(image, width, height, false)
+}
+
+fn resize(image: &blur::Image, width: u32, height: u32, cover: bool) -> blur::Image {
+    let mut scale_x = width as f32 / image.width as f32;
+    let mut scale_y = height as f32 / image.height as f32;
+    if cover {
+        scale_x = scale_x.max(scale_y);
+        scale_y = scale_x;
+    }
+
+    // Bilinear sampling skips pixels when shrinking a lot, average them first
+    let factor = (1.0 / scale_x.max(scale_y)) as u32;
+    if factor > 1 {
+        return resize(&downscale(image, factor), width, height, cover);
+    }
+
+    let src: &[u8] = bytemuck::cast_slice(&image.pixels);
+    let mut dst = vec![0u8; (width * height * 4) as usize];
+    let (max_x, max_y) = (image.width - 1, image.height - 1);
+    let offset_x = (image.width as f32 * scale_x - width as f32) / 2.0;
+    let offset_y = (image.height as f32 * scale_y - height as f32) / 2.0;
+
+    for y in 0..height {
+        let sy = ((y as f32 + offset_y + 0.5) / scale_y - 0.5).clamp(0.0, max_y as f32);
+        let (y0, fy) = (sy as u32, sy.fract());
+        let y1 = (y0 + 1).min(max_y);
+
+        for x in 0..width {
+            let sx = ((x as f32 + offset_x + 0.5) / scale_x - 0.5).clamp(0.0, max_x as f32);
+            let (x0, fx) = (sx as u32, sx.fract());
+            let x1 = (x0 + 1).min(max_x);
+
+            let o = ((y * width + x) * 4) as usize;
+            for c in 0..4 {
+                let p = |x: u32, y: u32| f32::from(src[((y * image.width + x) * 4) as usize + c]);
+                let top = p(x0, y0) * (1.0 - fx) + p(x1, y0) * fx;
+                let bottom = p(x0, y1) * (1.0 - fx) + p(x1, y1) * fx;
+                dst[o + c] = (top * (1.0 - fy) + bottom * fy).round() as u8;
+            }
+        }
+    }
+
+    blur::Image {
+        width,
+        height,
+        pixels: bytemuck::cast_vec(dst),
+    }
+}
+
+/// Blur image in place. With `downscale` set the image is replaced
+/// by the shrunk one, callers needing the original must clone it first.
+pub fn blur_image(image: &mut blur::Image, params: &BlurParams) {
+    let factor = params.downscale.max(1);
+
+    if factor > 1 {
+        *image = downscale(image, factor);
+    }
+
+    let radius = (params.radius / factor).max(1);
+
+    match resolve_method(params) {
+        BlurMethod::GPU => {
+            #[cfg(feature = "gpu")]
+            if gpu_blur(image, params, radius).is_none() {
+                log::warning!("GPU blur failed, fallback to CPU");
+                cpu_blur(image, params, radius);
+            }
             #[cfg(not(feature = "gpu"))]
             {
-                crate::log::warning!("GPU blur method is not supported, fallback to CPU");
-                do_blur(image, BlurMethod::CPU)
+                log::warning!("GPU blur method is not supported, fallback to CPU");
+                cpu_blur(image, params, radius);
             }
         }
-        BlurMethod::CPU => {
-            {
-                use libblur::{
-                    AnisotropicRadius,
-                    BlurImageMut,
-                    EdgeMode,
-                    EdgeMode2D,
-                    FastBlurChannels,
-                    ThreadingPolicy,
-                };
-
-                // TODO...
-                let data: &mut _ = unsafe { std::mem::transmute(image.as_mut_raw()) };
-                let mut blur = BlurImageMut::borrow(
-                    data,
-                    image.width,
-                    image.height,
-                    FastBlurChannels::Channels4,
-                );
-
-                //stack_blur(&mut blur, AnisotropicRadius::new(32), ThreadingPolicy::Adaptive);
-                //libblur::fast_gaussian(&mut blur, AnisotropicRadius::new(8), ThreadingPolicy::Adaptive, EdgeMode2D::new(EdgeMode::Clamp));
-
-                for _ in 0..3 {
-                    libblur::fast_gaussian_next(
-                        &mut blur,
-                        AnisotropicRadius::new(24),
-                        ThreadingPolicy::Adaptive,
-                        EdgeMode2D::new(EdgeMode::Reflect),
-                    );
-                }
+        BlurMethod::CPU | BlurMethod::Auto => cpu_blur(image, params, radius),
+    }
+}
+
+pub fn to_texture(image: &blur::Image) -> gdk::MemoryTexture {
+    gdk::MemoryTexture::new(
+        image.width as i32,
+        image.height as i32,
+        gdk::MemoryFormat::R8g8b8a8,
+        &glib::Bytes::from(bytemuck::cast_slice::<_, u8>(&image.pixels)),
+        (image.width * 4) as usize, // rowstride: bytes per row (width * 4 bytes for RGBA)
+    )
+}
+
+/// Download texture as tightly packed RGBA
+pub fn from_texture(texture: &gdk::Texture) -> blur::Image {
+    let mut downloader = gdk::TextureDownloader::new(texture);
+    downloader.set_format(gdk::MemoryFormat::R8g8b8a8);
+    let (bytes, stride) = downloader.download_bytes();
+
+    let width = texture.width() as u32;
+    let height = texture.height() as u32;
+    let row = (width * 4) as usize;
 
-                gtk::gdk::MemoryTexture::new(
-                    image.width as i32,
-                    image.height as i32,
-                    gtk::gdk::MemoryFormat::R8g8b8a8,
-                    &gtk::glib::Bytes::from_static(data),
-                    rowstride,
+    let pixels = bytes
+        .chunks(stride)
+        .take(height as usize)
+        .flat_map(|line| &line[..row])
+        .copied()
+        .collect::<Vec<u8>>();
+
+    blur::Image {
+        width,
+        height,
+        pixels: bytemuck::cast_vec(pixels),
+    }
+}
+
+#[cfg(feature = "gpu")]
+fn gpu_blur(image: &mut blur::Image, params: &BlurParams, radius: u32) -> Option<()> {
+    use futures::executor::block_on;
+    use image::Rgba;
+
+    if params.algorithm != BlurAlgorithm::Gaussian {
+        log::warning!(
+            "{:?} blur is not supported on GPU, using gaussian",
+            params.algorithm
+        );
+    }
+
+    if !crate::gpu::available() {
+        return None;
+    }
+
+    let filters = block_on(blur::Filters::new());
+    let mut operation = image.operation(&filters);
+
+    for _ in 0..params.passes {
+        operation = operation.gaussian_blur(radius as f32);
+    }
+
+    let result = block_on(operation.execute());
+    let buffer =
+        image::ImageBuffer::<Rgba<u8>, _>::from_raw(result.width, result.height, result.as_raw())?;
+
+    image.width = buffer.width();
+    image.height = buffer.height();
+    image.pixels = bytemuck::cast_vec(buffer.to_vec());
+
+    Some(())
+}
+
+fn cpu_blur(image: &mut blur::Image, params: &BlurParams, radius: u32) {
+    use libblur::{
+        AnisotropicRadius,
+        BlurImage,
+        BlurImageMut,
+        BoxBlurParameters,
+        EdgeMode,
+        EdgeMode2D,
+        FastBlurChannels,
+        ThreadingPolicy,
+    };
+
+    let (width, height) = (image.width, image.height);
+
+    for _ in 0..params.passes {
+        let result = match params.algorithm {
+            BlurAlgorithm::Gaussian => libblur::fast_gaussian_next(
+                &mut BlurImageMut::borrow(
+                    image.as_mut_raw(),
+                    width,
+                    height,
+                    FastBlurChannels::Channels4,
+                ),
+                AnisotropicRadius::new(radius),
+                ThreadingPolicy::Adaptive,
+                EdgeMode2D::new(EdgeMode::Reflect),
+            ),
+            BlurAlgorithm::Stack => libblur::stack_blur(
+                &mut BlurImageMut::borrow(
+                    image.as_mut_raw(),
+                    width,
+                    height,
+                    FastBlurChannels::Channels4,
+                ),
+                AnisotropicRadius::new(radius),
+                ThreadingPolicy::Adaptive,
+            ),
+            BlurAlgorithm::Box => {
+                // Box blur can not work in place
+                let src = image.as_mut_raw().to_vec();
+                libblur::box_blur(
+                    &BlurImage::borrow(&src, width, height, FastBlurChannels::Channels4),
+                    &mut BlurImageMut::borrow(
+                        image.as_mut_raw(),
+                        width,
+                        height,
+                        FastBlurChannels::Channels4,
+                    ),
+                    BoxBlurParameters::new(radius),
+                    ThreadingPolicy::Adaptive,
                 )
             }
+        };
+
+        if let Err(err) = result {
+            log::warning!("failed to blur image: {err}");
+            break;
         }
     }
 }
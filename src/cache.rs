@@ -0,0 +1,433 @@
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use gtk::{gdk, glib, prelude::*};
+use serde::Serialize;
+
+use crate::{
+    background::{Background, Fit},
+    blur::{BlurAlgorithm, BlurMethod, BlurParams, from_texture, resize_cover, resize_fill},
+    config::Config,
+    effects::{self, Effect},
+    log,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("no cache directory")]
+    NoDir,
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("failed to load {0:?}: {1}")]
+    Load(PathBuf, glib::Error),
+}
+
+/// Everything besides the source image that affects the rendered result.
+/// Serialized to TOML for the key, so it stays the same between builds.
+#[derive(Serialize)]
+struct KeyParams<'a> {
+    width: u32,
+    height: u32,
+    fit: Fit,
+    method: BlurMethod,
+    algorithm: BlurAlgorithm,
+    radius: u32,
+    passes: u32,
+    downscale: u32,
+    /// Last, as TOML tables follow plain values
+    effects: &'a [Effect],
+}
+
+/// Images with effects applied, ready to be shown on a monitor
+pub struct Cache {
+    dir: PathBuf,
+    /// Size limit in bytes
+    limit: u64,
+}
+
+impl Cache {
+    /// Cache in `$XDG_CACHE_HOME/waylock`, `limit` is in MiB
+    pub fn new(limit: u64) -> Result<Self, Error> {
+        let dir = xdg::BaseDirectories::with_prefix("waylock")
+            .get_cache_home()
+            .ok_or(Error::NoDir)?;
+
+        Ok(Self {
+            dir,
+            limit: limit * 1024 * 1024,
+        })
+    }
+
+    /// Entry name, changes with contents of the source and overlay files,
+    /// monitor size, fit or any effect parameter
+    fn key(
+        source: &Path,
+        width: u32,
+        height: u32,
+        fit: Fit,
+        effects: &[Effect],
+        params: &BlurParams,
+    ) -> io::Result<String> {
+        let key_params = KeyParams {
+            width,
+            height,
+            fit,
+            method: params.method,
+            algorithm: params.algorithm,
+            radius: params.radius,
+            passes: params.passes,
+            downscale: params.downscale,
+            effects,
+        };
+        let key_params = toml::to_string(&key_params).map_err(io::Error::other)?;
+
+        let mut checksum = glib::Checksum::new(glib::ChecksumType::Sha256)
+            .ok_or_else(|| io::Error::other("no SHA-256 support"))?;
+        checksum.update(&fs::read(source)?);
+        checksum.update(key_params.as_bytes());
+
+        // Overlay missing now is skipped when rendering, length keeps contents apart
+        for effect in effects {
+            if let Effect::Overlay { path, .. } = effect {
+                let contents = fs::read(path).unwrap_or_default();
+                checksum.update(&(contents.len() as u64).to_le_bytes());
+                checksum.update(&contents);
+            }
+        }
+
+        let digest = checksum
+            .string()
+            .ok_or_else(|| io::Error::other("failed to compute digest"))?;
+
+        Ok(format!("{digest}.png"))
+    }
+
+    fn load(&self, name: &str) -> Option<gdk::Texture> {
+        let path = self.dir.join(name);
+        let texture = gdk::Texture::from_filename(&path).ok()?;
+
+        // Eviction goes by modification time, so mark it as recently used
+        if let Err(err) = fs::File::options()
+            .append(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            log::info!("failed to touch {path:?}: {err}");
+        }
+
+        Some(texture)
+    }
+
+    fn store(&self, name: &str, texture: &gdk::Texture) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        // Rename is atomic, so a crash never leaves a half written entry
+        let path = self.dir.join(name);
+        let tmp = self.dir.join(format!(".{name}.{}.tmp", std::process::id()));
+        fs::write(&tmp, texture.save_to_png_bytes())?;
+        fs::rename(&tmp, &path).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })?;
+
+        // Entry is stored already, a full cache is not a reason to fail
+        if let Err(err) = self.evict(name) {
+            log::warning!("failed to evict cache entries: {err}");
+        }
+
+        Ok(())
+    }
+
+    /// Remove least recently used entries until cache fits in the limit.
+    /// `keep` is never removed, neither are files other processes are writing.
+    fn evict(&self, keep: &str) -> io::Result<()> {
+        let mut entries = fs::read_dir(&self.dir)?
+            .flatten()
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+            })
+            .filter(|(_, _, path)| path.extension().is_some_and(|ext| ext == "png"))
+            .collect::<Vec<_>>();
+
+        entries.sort();
+
+        let mut size = entries.iter().map(|(_, len, _)| len).sum::<u64>();
+
+        for (_, len, path) in entries {
+            if size <= self.limit {
+                break;
+            }
+
+            if path.file_name().is_some_and(|name| name == keep) {
+                continue;
+            }
+
+            // Another instance may have evicted it first
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => size -= len,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// Load image, fit it to `width`x`height` and apply effects, or take it from cache.
+    /// It may take a while so shall be called from a worker thread.
+    pub fn render(
+        &self,
+        source: &Path,
+        width: u32,
+        height: u32,
+        fit: Fit,
+        effects: &[Effect],
+        params: &BlurParams,
+    ) -> Result<gdk::Texture, Error> {
+        let name = Self::key(source, width, height, fit, effects, params)?;
+
+        if let Some(texture) = self.load(&name) {
+            return Ok(texture);
+        }
+
+        let texture =
+            gdk::Texture::from_filename(source).map_err(|err| Error::Load(source.into(), err))?;
+        let mut image = resize(&from_texture(&texture), width, height, fit);
+        let texture = effects::render(&mut image, effects, params).upcast::<gdk::Texture>();
+
+        if let Err(err) = self.store(&name, &texture) {
+            log::warning!("failed to cache {source:?}: {err}");
+        }
+
+        Ok(texture)
+    }
+}
+
+/// Scale `image` for a `width`x`height` monitor the way `fit` shows it.
+/// Only `Cover` crops, `Contain` and `ScaleDown` keep the aspect ratio within the monitor.
+fn resize(image: &blur::Image, width: u32, height: u32, fit: Fit) -> blur::Image {
+    let scale = (width as f32 / image.width as f32).min(height as f32 / image.height as f32);
+    let scale = match fit {
+        Fit::Cover => return resize_cover(image, width, height),
+        Fit::Fill => return resize_fill(image, width, height),
+        Fit::Contain => scale,
+        Fit::ScaleDown => scale.min(1.0),
+    };
+    let size = |len: u32| ((len as f32 * scale).round() as u32).max(1);
+
+    resize_fill(image, size(image.width), size(image.height))
+}
+
+/// Size of monitor in physical pixels
+pub fn monitor_size(monitor: &gdk::Monitor) -> (u32, u32) {
+    let geometry = monitor.geometry();
+    let scale = monitor.scale();
+
+    (
+        (f64::from(geometry.width()) * scale) as u32,
+        (f64::from(geometry.height()) * scale) as u32,
+    )
+}
+
+/// `waylock cache warm`, render images with effects for every connected monitor
+pub fn warm(config: &Config) -> Result<(), Error> {
+    let cache = Cache::new(config.get_cache_size())?;
+    let backgrounds = config.get_backgrounds();
+    let params = config.get_blur();
+
+    let monitors = gdk::Display::default()
+        .map(|display| display.monitors())
+        .into_iter()
+        .flat_map(|monitors| {
+            monitors
+                .iter::<gdk::Monitor>()
+                .flatten()
+                .collect::<Vec<_>>()
+        });
+
+    for monitor in monitors {
+        let (width, height) = monitor_size(&monitor);
+
+        for background in backgrounds.get(Some(&monitor)) {
+            if let Background::Image {
+                path,
+                fit,
+                effects: Some(effects),
+            } = background
+            {
+                match cache.render(path, width, height, *fit, effects, &params) {
+                    Ok(_) => log::info!("cached {path:?} for {width}x{height}"),
+                    Err(err) => log::warning!("failed to cache {path:?}: {err}"),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    /// Empty cache in a directory of its own
+    fn cache(name: &str, limit: u64) -> Cache {
+        let dir = std::env::temp_dir().join(format!("waylock-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        Cache { dir, limit }
+    }
+
+    /// File of `len` bytes modified `age` seconds ago
+    fn entry(cache: &Cache, name: &str, len: usize, age: u64) {
+        let path = cache.dir.join(name);
+        fs::write(&path, vec![0; len]).unwrap();
+        fs::File::options()
+            .append(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now() - Duration::from_secs(age)))
+            .unwrap();
+    }
+
+    fn names(cache: &Cache) -> Vec<String> {
+        let mut names = fs::read_dir(&cache.dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn key_is_stable() {
+        let source = std::env::temp_dir().join(format!("waylock-key-{}", std::process::id()));
+        fs::write(&source, b"image").unwrap();
+
+        let effects = [Effect::Blur, Effect::Pixelate { size: 4 }];
+        let params = BlurParams::default();
+        let key = Cache::key(&source, 1920, 1080, Fit::Cover, &effects, &params).unwrap();
+
+        assert_eq!(
+            key,
+            Cache::key(&source, 1920, 1080, Fit::Cover, &effects, &params).unwrap()
+        );
+        assert_eq!(key.len(), 64 + ".png".len());
+
+        assert_ne!(
+            key,
+            Cache::key(&source, 1080, 1920, Fit::Cover, &effects, &params).unwrap()
+        );
+        assert_ne!(
+            key,
+            Cache::key(&source, 1920, 1080, Fit::Cover, &effects[..1], &params).unwrap()
+        );
+
+        let params = BlurParams {
+            radius: params.radius + 1,
+            ..params
+        };
+        assert_ne!(
+            key,
+            Cache::key(&source, 1920, 1080, Fit::Cover, &effects, &params).unwrap()
+        );
+
+        fs::write(&source, b"other image").unwrap();
+        assert_ne!(
+            key,
+            Cache::key(&source, 1920, 1080, Fit::Cover, &effects, &params).unwrap()
+        );
+
+        fs::remove_file(&source).unwrap();
+    }
+
+    #[test]
+    fn key_follows_fit_and_overlay() {
+        let dir = std::env::temp_dir();
+        let source = dir.join(format!("waylock-key-fit-{}", std::process::id()));
+        let overlay = dir.join(format!("waylock-key-overlay-{}", std::process::id()));
+        fs::write(&source, b"image").unwrap();
+        fs::write(&overlay, b"overlay").unwrap();
+
+        let effects = [Effect::Overlay {
+            path: overlay.clone(),
+            opacity: 0.5,
+        }];
+        let params = BlurParams::default();
+        let key = Cache::key(&source, 1920, 1080, Fit::Cover, &effects, &params).unwrap();
+
+        assert_ne!(
+            key,
+            Cache::key(&source, 1920, 1080, Fit::Contain, &effects, &params).unwrap()
+        );
+
+        // Same path, new contents
+        fs::write(&overlay, b"other overlay").unwrap();
+        assert_ne!(
+            key,
+            Cache::key(&source, 1920, 1080, Fit::Cover, &effects, &params).unwrap()
+        );
+
+        fs::remove_file(&source).unwrap();
+        fs::remove_file(&overlay).unwrap();
+    }
+
+    #[test]
+    fn resize_by_fit() {
+        let image = blur::Image {
+            width: 4,
+            height: 2,
+            pixels: bytemuck::cast_vec(vec![255u8; 4 * 8]),
+        };
+        let size = |fit| {
+            let image = resize(&image, 16, 16, fit);
+            (image.width, image.height)
+        };
+
+        assert_eq!(size(Fit::Cover), (16, 16));
+        assert_eq!(size(Fit::Fill), (16, 16));
+        assert_eq!(size(Fit::Contain), (16, 8));
+        assert_eq!(size(Fit::ScaleDown), (4, 2));
+    }
+
+    #[test]
+    fn evict_oldest() {
+        let cache = cache("evict", 250);
+        entry(&cache, "old.png", 100, 30);
+        entry(&cache, "older.png", 100, 60);
+        entry(&cache, "new.png", 100, 0);
+
+        cache.evict("new.png").unwrap();
+
+        assert_eq!(names(&cache), ["new.png", "old.png"]);
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn evict_keeps_stored_and_temporary() {
+        let cache = cache("evict-keep", 150);
+        entry(&cache, "stored.png", 100, 60);
+        entry(&cache, "other.png", 100, 0);
+        entry(&cache, ".writing.png.1.tmp", 100, 90);
+
+        cache.evict("stored.png").unwrap();
+
+        assert_eq!(names(&cache), [".writing.png.1.tmp", "stored.png"]);
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+}
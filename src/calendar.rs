@@ -0,0 +1,700 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use gtk::glib;
+
+use crate::{log, widgets::clock::time_zone};
+
+/// Recurring events are not expanded past this many occurrences
+const MAX_OCCURRENCES: usize = 100_000;
+
+/// Occurrence of an event today
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub summary: String,
+    pub start: glib::DateTime,
+    pub end: glib::DateTime,
+    pub all_day: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug)]
+struct Rule {
+    frequency: Frequency,
+    interval: i32,
+    count: Option<usize>,
+    until: Option<glib::DateTime>,
+    /// Days of week for weekly rules, 1 is Monday
+    weekdays: Vec<i32>,
+}
+
+#[derive(Debug, Default)]
+struct Component {
+    uid: Option<String>,
+    summary: Option<String>,
+    start: Option<(glib::DateTime, bool)>,
+    end: Option<glib::DateTime>,
+    /// Seconds
+    duration: Option<i64>,
+    rule: Option<Rule>,
+    exdates: Vec<glib::DateTime>,
+    recurrence_id: Option<glib::DateTime>,
+}
+
+/// `.ics` files under `path`, vdirsyncer keeps one directory per calendar
+fn ics_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    ics_files(&entry.path(), files);
+                }
+            }
+            Err(err) => log::warning!("failed to read {path:?}: {err}"),
+        }
+    } else if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ics"))
+    {
+        files.push(path.into());
+    }
+}
+
+/// Undo line folding, continuation lines start with a space or a tab
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.into()),
+        }
+    }
+
+    lines
+}
+
+/// `NAME;PARAM=VALUE:value`, colons inside quoted parameters do not count
+fn split_line(line: &str) -> Option<(&str, HashMap<String, String>, &str)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?;
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"').into()))
+        .collect();
+
+    Some((name, params, value))
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n' | 'N') => result.push('\n'),
+                Some(c) => result.push(c),
+                None => {}
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// `20240115` is a date, `20240115T093000` is local, floating or `TZID` time, trailing `Z` is UTC
+fn parse_time(value: &str, params: &HashMap<String, String>) -> Option<(glib::DateTime, bool)> {
+    let number = |range: std::ops::Range<usize>| value.get(range)?.parse::<i32>().ok();
+    let (year, month, day) = (number(0..4)?, number(4..6)?, number(6..8)?);
+
+    if value.len() == 8 {
+        return glib::DateTime::new(&glib::TimeZone::local(), year, month, day, 0, 0, 0.0)
+            .ok()
+            .map(|date| (date, true));
+    }
+
+    let (hour, minute, second) = (number(9..11)?, number(11..13)?, number(13..15)?);
+
+    let zone = if value.ends_with('Z') {
+        glib::TimeZone::utc()
+    } else {
+        params
+            .get("TZID")
+            .and_then(|id| time_zone(id))
+            .unwrap_or_else(glib::TimeZone::local)
+    };
+
+    glib::DateTime::new(&zone, year, month, day, hour, minute, f64::from(second))
+        .ok()
+        .map(|time| (time, false))
+}
+
+/// `P1D`, `PT1H30M`, `P2W`, in seconds
+fn parse_duration(value: &str) -> Option<i64> {
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let mut seconds = 0;
+    let mut number = 0;
+
+    for c in value.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number = number * 10 + i64::from(c.to_digit(10)?),
+            'T' => {}
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                seconds += number
+                    * match c {
+                        'W' => 7 * 24 * 3600,
+                        'D' => 24 * 3600,
+                        'H' => 3600,
+                        'M' => 60,
+                        _ => 1,
+                    };
+                number = 0;
+            }
+            _ => return None,
+        }
+    }
+
+    Some(sign * seconds)
+}
+
+fn parse_rule(value: &str) -> Option<Rule> {
+    let mut frequency = None;
+    let mut rule = Rule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        weekdays: Vec::new(),
+    };
+
+    for (key, value) in value.split(';').filter_map(|part| part.split_once('=')) {
+        match key {
+            "FREQ" => {
+                frequency = Some(match value {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return None,
+                });
+            }
+            "INTERVAL" => rule.interval = value.parse().ok().filter(|&n| n > 0)?,
+            "COUNT" => rule.count = Some(value.parse().ok()?),
+            "UNTIL" => rule.until = Some(parse_time(value, &HashMap::new())?.0),
+            "BYDAY" => {
+                for day in value.split(',') {
+                    rule.weekdays.push(
+                        ["MO", "TU", "WE", "TH", "FR", "SA", "SU"]
+                            .iter()
+                            .position(|name| *name == day)? as i32
+                            + 1,
+                    );
+                }
+            }
+            "WKST" => {}
+            // Rare in personal calendars, such events show only their first occurrence
+            _ => return None,
+        }
+    }
+
+    rule.frequency = frequency?;
+
+    // Only weekly rules may pick days of week without an ordinal
+    (rule.weekdays.is_empty() || rule.frequency == Frequency::Weekly).then_some(rule)
+}
+
+fn parse(text: &str) -> Vec<Component> {
+    let mut components = Vec::new();
+    let mut current = None::<Component>;
+    // Alarms and other nested components have properties of their own
+    let mut nested = 0;
+
+    for line in unfold(text) {
+        let Some((name, params, value)) = split_line(&line) else {
+            continue;
+        };
+
+        let name = name.to_ascii_uppercase();
+
+        match (name.as_str(), value) {
+            ("BEGIN", "VEVENT") => {
+                current = Some(Component::default());
+                nested = 0;
+                continue;
+            }
+            ("END", "VEVENT") => {
+                components.extend(current.take());
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some(event) = current.as_mut() else {
+            continue;
+        };
+
+        match name.as_str() {
+            "BEGIN" => nested += 1,
+            "END" => nested -= 1,
+            _ if nested > 0 => {}
+            "UID" => event.uid = Some(value.into()),
+            "SUMMARY" => event.summary = Some(unescape(value)),
+            "DTSTART" => event.start = parse_time(value, &params),
+            "DTEND" => event.end = parse_time(value, &params).map(|(end, _)| end),
+            "DURATION" => event.duration = parse_duration(value),
+            "RRULE" => {
+                event.rule = parse_rule(value);
+                if event.rule.is_none() {
+                    log::warning!(
+                        "unsupported RRULE {value:?}, only the first occurrence is shown"
+                    );
+                }
+            }
+            "EXDATE" => event.exdates.extend(
+                value
+                    .split(',')
+                    .filter_map(|value| parse_time(value, &params))
+                    .map(|(time, _)| time),
+            ),
+            "RECURRENCE-ID" => {
+                event.recurrence_id = parse_time(value, &params).map(|(time, _)| time);
+            }
+            _ => {}
+        }
+    }
+
+    components
+}
+
+/// Same wall clock time `months` or `days` later, `None` if there is no such day,
+/// e.g. 31st in a shorter month, which is skipped rather than moved
+fn shift(start: &glib::DateTime, months: i32, days: i32) -> Option<glib::DateTime> {
+    let (year, month, day) = if days == 0 {
+        let (year, month, day) = start.ymd();
+        let month = month - 1 + months;
+        (year + month.div_euclid(12), month.rem_euclid(12) + 1, day)
+    } else {
+        start.add_days(days).ok()?.ymd()
+    };
+
+    glib::DateTime::new(
+        &start.timezone(),
+        year,
+        month,
+        day,
+        start.hour(),
+        start.minute(),
+        start.seconds(),
+    )
+    .ok()
+}
+
+/// Period to expand `rule` from, a bit before the first one with occurrences at or after `from`
+fn first_period(start: &glib::DateTime, rule: &Rule, from: &glib::DateTime) -> i32 {
+    // Every occurrence counts towards `COUNT`, so none may be skipped
+    if rule.count.is_some() || from <= start {
+        return 0;
+    }
+
+    let ((start_year, start_month, _), (from_year, from_month, _)) = (start.ymd(), from.ymd());
+    let days = (from.difference(start).as_seconds() / (24 * 3600)) as i32;
+
+    let periods = match rule.frequency {
+        Frequency::Daily => days,
+        Frequency::Weekly => days / 7,
+        Frequency::Monthly => (from_year - start_year) * 12 + from_month - start_month,
+        Frequency::Yearly => from_year - start_year,
+    };
+
+    // One period earlier covers daylight saving shifts and weeks starting on Monday
+    (periods / rule.interval - 1).max(0)
+}
+
+/// Starts of occurrences, in order, from `from` until `before`
+fn occurrences(
+    start: &glib::DateTime,
+    rule: Option<&Rule>,
+    from: &glib::DateTime,
+    before: &glib::DateTime,
+) -> Vec<glib::DateTime> {
+    let Some(rule) = rule else {
+        return vec![start.clone()];
+    };
+
+    let mut starts = Vec::new();
+    let mut count = 0;
+    let first = first_period(start, rule, from);
+
+    for period in first..first.saturating_add(MAX_OCCURRENCES as i32) {
+        let step = period * rule.interval;
+        let candidates = match rule.frequency {
+            Frequency::Daily => vec![shift(start, 0, step)],
+            Frequency::Monthly => vec![shift(start, step, 0)],
+            Frequency::Yearly => vec![shift(start, step * 12, 0)],
+            Frequency::Weekly if rule.weekdays.is_empty() => vec![shift(start, 0, step * 7)],
+            Frequency::Weekly => {
+                let mut weekdays = rule.weekdays.clone();
+                weekdays.sort_unstable();
+
+                // Weeks start on Monday
+                let monday = step * 7 - (start.day_of_week() - 1);
+                weekdays
+                    .into_iter()
+                    .map(|weekday| shift(start, 0, monday + weekday - 1))
+                    .collect()
+            }
+        };
+
+        for candidate in candidates.into_iter().flatten() {
+            if candidate < *start {
+                continue;
+            }
+
+            if candidate >= *before
+                || rule.until.as_ref().is_some_and(|until| candidate > *until)
+                || rule.count.is_some_and(|limit| count >= limit)
+            {
+                return starts;
+            }
+
+            count += 1;
+            if candidate >= *from {
+                starts.push(candidate);
+            }
+        }
+    }
+
+    starts
+}
+
+/// Events of `components` overlapping `[from, to)`
+fn expand(components: Vec<Component>, from: &glib::DateTime, to: &glib::DateTime) -> Vec<Event> {
+    // Modified occurrences come as separate components with the same `UID`
+    let overridden = components
+        .iter()
+        .filter_map(|component| {
+            Some((
+                component.uid.clone()?,
+                component.recurrence_id.as_ref()?.to_unix(),
+            ))
+        })
+        .collect::<HashSet<_>>();
+
+    let mut events = Vec::new();
+
+    for component in components {
+        let Some((start, all_day)) = component.start else {
+            continue;
+        };
+
+        let duration = match (&component.end, component.duration) {
+            (Some(end), _) => end.difference(&start).as_seconds(),
+            (None, Some(duration)) => duration,
+            (None, None) if all_day => 24 * 3600,
+            (None, None) => 0,
+        };
+
+        let recurring = component.recurrence_id.is_none();
+
+        // Occurrences starting earlier may still last into `from`
+        let Ok(earliest) = from.add_seconds(-(duration.max(0) as f64)) else {
+            continue;
+        };
+
+        for start in occurrences(&start, component.rule.as_ref(), &earliest, to) {
+            let Ok(end) = start.add_seconds(duration as f64) else {
+                continue;
+            };
+
+            let excluded = component.exdates.iter().any(|date| *date == start)
+                || recurring
+                    && component
+                        .uid
+                        .as_ref()
+                        .is_some_and(|uid| overridden.contains(&(uid.clone(), start.to_unix())));
+
+            if !excluded && start < *to && (end > *from || start >= *from) {
+                events.push(Event {
+                    summary: component.summary.clone().unwrap_or_default(),
+                    start: start.to_local().unwrap_or(start),
+                    end: end.to_local().unwrap_or(end),
+                    all_day,
+                });
+            }
+        }
+    }
+
+    events
+}
+
+/// Today's events from `.ics` files or directories of them, all-day events first.
+/// Reads files so shall be called from a worker thread.
+pub fn today(paths: &[PathBuf]) -> Vec<Event> {
+    let Ok(now) = glib::DateTime::now_local() else {
+        return Vec::new();
+    };
+
+    let (year, month, day) = now.ymd();
+    let Some(from) = glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok() else {
+        return Vec::new();
+    };
+    let Ok(to) = from.add_days(1) else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    for path in paths {
+        ics_files(path, &mut files);
+    }
+
+    let mut events = files
+        .iter()
+        .filter_map(|file| {
+            fs::read_to_string(file)
+                .map_err(|err| log::warning!("failed to read {file:?}: {err}"))
+                .ok()
+        })
+        .flat_map(|text| expand(parse(&text), &from, &to))
+        .collect::<Vec<_>>();
+
+    events.sort_by(|a, b| (!a.all_day, &a.start).cmp(&(!b.all_day, &b.start)));
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: i32, day: i32, hour: i32, minute: i32) -> glib::DateTime {
+        glib::DateTime::from_utc(year, month, day, hour, minute, 0.0).unwrap()
+    }
+
+    /// Events of UTC day, as (summary, start)
+    fn on(text: &str, year: i32, month: i32, day: i32) -> Vec<(String, i64)> {
+        let from = utc(year, month, day, 0, 0);
+        let to = from.add_days(1).unwrap();
+
+        let mut events = expand(parse(text), &from, &to)
+            .into_iter()
+            .map(|event| (event.summary, event.start.to_unix()))
+            .collect::<Vec<_>>();
+        events.sort_by_key(|(_, start)| *start);
+        events
+    }
+
+    fn calendar(events: &[&str]) -> String {
+        let events = events
+            .iter()
+            .map(|event| format!("BEGIN:VEVENT\n{event}\nEND:VEVENT\n"))
+            .collect::<String>();
+
+        format!("BEGIN:VCALENDAR\nVERSION:2.0\n{events}END:VCALENDAR\n")
+    }
+
+    fn daily(rule: &str) -> String {
+        calendar(&[&format!(
+            "UID:daily\nSUMMARY:Standup\nDTSTART:20240101T090000Z\nDURATION:PT15M\nRRULE:{rule}"
+        )])
+    }
+
+    #[test]
+    fn unfolding() {
+        assert_eq!(
+            unfold("SUMMARY:Team\r\n  meeting\r\n\tabout things\r\nUID:1"),
+            ["SUMMARY:Team meetingabout things", "UID:1"]
+        );
+
+        let text = calendar(&["UID:1\nSUMMARY:Lunch\\, then\n  coffee\nDTSTART:20240115T120000Z"]);
+        assert_eq!(
+            on(&text, 2024, 1, 15),
+            [(
+                String::from("Lunch, then coffee"),
+                utc(2024, 1, 15, 12, 0).to_unix()
+            )]
+        );
+    }
+
+    #[test]
+    fn values() {
+        let (time, all_day) = parse_time("20240115T093000Z", &HashMap::new()).unwrap();
+        assert_eq!(time, utc(2024, 1, 15, 9, 30));
+        assert!(!all_day);
+
+        let params = HashMap::from([(String::from("TZID"), "Europe/Berlin".into())]);
+        let (time, all_day) = parse_time("20240115T093000", &params).unwrap();
+        assert_eq!(time, utc(2024, 1, 15, 8, 30));
+        assert!(!all_day);
+
+        // Summer time
+        let (time, _) = parse_time("20240715T093000", &params).unwrap();
+        assert_eq!(time, utc(2024, 7, 15, 7, 30));
+
+        let (date, all_day) = parse_time("20240115", &HashMap::new()).unwrap();
+        assert_eq!(date.ymd(), (2024, 1, 15));
+        assert_eq!((date.hour(), date.minute()), (0, 0));
+        assert!(all_day);
+
+        assert!(parse_time("2024011", &HashMap::new()).is_none());
+        assert!(parse_time("20241315T093000Z", &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn all_day() {
+        let text = calendar(&["UID:1\nSUMMARY:Holiday\nDTSTART;VALUE=DATE:20240115"]);
+        let from = glib::DateTime::from_local(2024, 1, 15, 0, 0, 0.0).unwrap();
+        let to = from.add_days(1).unwrap();
+
+        let events = expand(parse(&text), &from, &to);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].all_day);
+        assert_eq!(events[0].end, to);
+
+        let to = from.add_days(2).unwrap();
+        let from = from.add_days(1).unwrap();
+        assert!(expand(parse(&text), &from, &to).is_empty());
+    }
+
+    #[test]
+    fn daily_interval() {
+        let text = daily("FREQ=DAILY;INTERVAL=2");
+
+        // 60 days after the start
+        assert_eq!(
+            on(&text, 2024, 3, 1),
+            [(String::from("Standup"), utc(2024, 3, 1, 9, 0).to_unix())]
+        );
+        assert!(on(&text, 2024, 3, 2).is_empty());
+
+        // Far from the start, expansion jumps ahead instead of walking every day
+        assert_eq!(
+            on(&text, 2030, 1, 1),
+            [(String::from("Standup"), utc(2030, 1, 1, 9, 0).to_unix())]
+        );
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        let text = calendar(&[
+            "UID:gym\nSUMMARY:Gym\nDTSTART:20240101T180000Z\nDTEND:20240101T190000Z\nRRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR",
+        ]);
+
+        for (day, expected) in [
+            (10, true),
+            (11, false),
+            (12, true),
+            (13, false),
+            (14, true),
+            (15, false),
+        ] {
+            assert_eq!(!on(&text, 2024, 6, day).is_empty(), expected, "June {day}");
+        }
+
+        let text = calendar(&[
+            "UID:review\nSUMMARY:Review\nDTSTART:20240103T100000Z\nRRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,WE",
+        ]);
+
+        // Tuesday before the start is not an occurrence
+        assert!(on(&text, 2024, 1, 2).is_empty());
+        assert!(!on(&text, 2024, 1, 3).is_empty());
+        assert!(on(&text, 2024, 1, 9).is_empty());
+        assert!(!on(&text, 2024, 1, 16).is_empty());
+        assert!(!on(&text, 2024, 1, 17).is_empty());
+    }
+
+    #[test]
+    fn count_and_until() {
+        let text = daily("FREQ=DAILY;COUNT=3");
+        assert!(!on(&text, 2024, 1, 3).is_empty());
+        assert!(on(&text, 2024, 1, 4).is_empty());
+        assert!(on(&text, 2025, 1, 1).is_empty());
+
+        let text = daily("FREQ=DAILY;UNTIL=20240103T090000Z");
+        assert!(!on(&text, 2024, 1, 3).is_empty());
+        assert!(on(&text, 2024, 1, 4).is_empty());
+    }
+
+    #[test]
+    fn exdate() {
+        let text = calendar(&[
+            "UID:daily\nSUMMARY:Standup\nDTSTART:20240101T090000Z\nRRULE:FREQ=DAILY\nEXDATE:20240102T090000Z,20240104T090000Z",
+        ]);
+
+        assert!(!on(&text, 2024, 1, 1).is_empty());
+        assert!(on(&text, 2024, 1, 2).is_empty());
+        assert!(!on(&text, 2024, 1, 3).is_empty());
+        assert!(on(&text, 2024, 1, 4).is_empty());
+    }
+
+    #[test]
+    fn recurrence_id() {
+        let text = calendar(&[
+            "UID:daily\nSUMMARY:Standup\nDTSTART:20240101T090000Z\nRRULE:FREQ=DAILY",
+            "UID:daily\nSUMMARY:Moved\nRECURRENCE-ID:20240102T090000Z\nDTSTART:20240102T150000Z",
+        ]);
+
+        assert_eq!(
+            on(&text, 2024, 1, 2),
+            [(String::from("Moved"), utc(2024, 1, 2, 15, 0).to_unix())]
+        );
+        assert_eq!(
+            on(&text, 2024, 1, 3),
+            [(String::from("Standup"), utc(2024, 1, 3, 9, 0).to_unix())]
+        );
+    }
+
+    #[test]
+    fn long_events() {
+        // Started the day before, still running
+        let text = calendar(&[
+            "UID:trip\nSUMMARY:Trip\nDTSTART:20240101T120000Z\nDURATION:P2D\nRRULE:FREQ=MONTHLY",
+        ]);
+
+        assert_eq!(
+            on(&text, 2025, 3, 2),
+            [(String::from("Trip"), utc(2025, 3, 1, 12, 0).to_unix())]
+        );
+        assert!(on(&text, 2025, 3, 4).is_empty());
+    }
+
+    #[test]
+    fn unsupported_rules() {
+        assert!(parse_rule("FREQ=MONTHLY;BYMONTHDAY=15").is_none());
+        assert!(parse_rule("FREQ=YEARLY;BYMONTH=3").is_none());
+        assert!(parse_rule("FREQ=MONTHLY;BYDAY=1MO").is_none());
+        assert!(parse_rule("FREQ=DAILY;BYDAY=MO").is_none());
+
+        // Only the first occurrence is shown
+        let text = daily("FREQ=MONTHLY;BYMONTHDAY=15");
+        assert!(!on(&text, 2024, 1, 1).is_empty());
+        assert!(on(&text, 2024, 1, 15).is_empty());
+    }
+}
@@ -1,11 +1,29 @@
 use std::{
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::{blur::BlurMethod, log};
+use crate::{
+    background::{BackgroundChain, Backgrounds},
+    blur::{BlurAlgorithm, BlurMethod, BlurParams},
+    effects::{Effect, Effects},
+    log,
+    screenshot::Backend as ScreenshotBackend,
+    theme::ColorScheme,
+    video::Decoder,
+    widgets::{
+        WidgetKind,
+        Widgets,
+        clock::{ClockStyle, ZoneClock, ZoneClocks},
+        events::Calendars,
+        greeting::{GreetingVariants, Templates},
+        powerbar::{PowerAction, PowerActionConfig, PowerActions},
+        slideshow::{SlideshowOrder, SlideshowParams},
+    },
+};
 
 #[derive(clap::Parser, Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
@@ -24,10 +42,56 @@ pub struct Config {
     #[serde(default = "default::style")]
     style: Option<PathBuf>,
 
-    // Path to background
+    /// Background: screenshot, image, video, directory, glob or color,
+    /// comma separated alternatives are tried in order
     #[arg(long, short = 'b')]
     #[serde(default)]
-    background: Option<PathBuf>,
+    background: Option<BackgroundChain>,
+
+    /// Background used instead of `background` when dark variant is active
+    #[arg(long)]
+    #[serde(default)]
+    dark_background: Option<BackgroundChain>,
+
+    /// Backgrounds of specific monitors by connector name
+    #[arg(skip)]
+    #[serde(default)]
+    monitor_backgrounds: Option<HashMap<String, BackgroundChain>>,
+
+    /// Choose dark or light variant of style and background
+    #[arg(long, value_enum)]
+    #[serde(default)]
+    color_scheme: Option<ColorScheme>,
+
+    /// Start of the day for `time` color scheme, HH:MM
+    #[arg(long)]
+    #[serde(default)]
+    day_start: Option<String>,
+
+    /// Start of the night for `time` color scheme, HH:MM
+    #[arg(long)]
+    #[serde(default)]
+    night_start: Option<String>,
+
+    /// Latitude to compute sunrise and sunset instead of `day-start` and `night-start`
+    #[arg(long, requires = "longitude", allow_negative_numbers = true)]
+    #[serde(default)]
+    latitude: Option<f64>,
+
+    /// Longitude to compute sunrise and sunset
+    #[arg(long, requires = "latitude", allow_negative_numbers = true)]
+    #[serde(default)]
+    longitude: Option<f64>,
+
+    /// GtkBuilder file with placeholders for widgets
+    #[arg(long, short = 'x')]
+    #[serde(default)]
+    layout: Option<PathBuf>,
+
+    /// Widgets to show, in order within a common container
+    #[arg(long, value_enum, value_delimiter = ',')]
+    #[serde(default)]
+    widgets: Option<Vec<WidgetKind>>,
 
     /// Idle timeout in seconds
     #[arg(long)]
@@ -49,10 +113,147 @@ pub struct Config {
     #[arg(long)]
     #[serde(default = "default::date_format")]
     date_format: Option<String>,
-    // TODO
-    // #[arg(long)]
-    // #[serde()]
-    // blur_method: Option<BlurMethod>,
+
+    /// Digital, analog or both clocks
+    #[arg(long, value_enum)]
+    #[serde(default)]
+    clock_style: Option<ClockStyle>,
+
+    /// Show seconds hand of analog clock
+    #[arg(long)]
+    #[serde(default)]
+    clock_seconds: Option<bool>,
+
+    /// Additional clocks for other time zones
+    #[arg(skip)]
+    #[serde(default)]
+    time_zones: Option<Vec<ZoneClock>>,
+
+    /// `.ics` files or directories of them to show today's events from
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    calendars: Option<Vec<PathBuf>>,
+
+    /// Pango markup above the clock with `{user}`, `{real_name}`, `{hostname}`,
+    /// `{greeting}`, `{locked_since}`, `{locked_for}` and `{failed_attempts}`
+    #[arg(long)]
+    #[serde(default)]
+    greeting: Option<String>,
+
+    /// Greeting templates for morning, afternoon, evening and night
+    #[arg(skip)]
+    #[serde(default)]
+    greeting_variants: Option<GreetingVariants>,
+
+    /// Buttons of the power bar in order
+    #[arg(long, value_enum, value_delimiter = ',')]
+    #[serde(default)]
+    power_actions: Option<Vec<PowerAction>>,
+
+    /// Custom command, icon and confirmation of power actions
+    #[arg(skip)]
+    #[serde(default)]
+    power: Option<HashMap<PowerAction, PowerActionConfig>>,
+
+    /// Where to blur screenshot background
+    #[arg(long, value_enum)]
+    #[serde(default)]
+    blur_method: Option<BlurMethod>,
+
+    /// Blur algorithm
+    #[arg(long, value_enum)]
+    #[serde(default)]
+    blur_algorithm: Option<BlurAlgorithm>,
+
+    /// Blur radius in pixels
+    #[arg(long)]
+    #[serde(default)]
+    blur_radius: Option<u32>,
+
+    /// Number of blur passes
+    #[arg(long)]
+    #[serde(default)]
+    blur_passes: Option<u32>,
+
+    /// Shrink screenshot by this factor before blurring
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    #[serde(default)]
+    blur_downscale: Option<u32>,
+
+    /// Duration of progressive blur animation in milliseconds, 0 disables it
+    #[arg(long)]
+    #[serde(default)]
+    blur_animation: Option<u32>,
+
+    /// Number of intermediate frames of blur animation
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    #[serde(default)]
+    blur_animation_steps: Option<u32>,
+
+    /// Seconds between slideshow images, when background is a directory or glob
+    #[arg(long)]
+    #[serde(default)]
+    slideshow_interval: Option<u64>,
+
+    /// Slideshow cross-fade duration in milliseconds
+    #[arg(long)]
+    #[serde(default)]
+    slideshow_transition: Option<u32>,
+
+    /// Order of slideshow images
+    #[arg(long, value_enum)]
+    #[serde(default)]
+    slideshow_order: Option<SlideshowOrder>,
+
+    /// Show different slideshow images on each monitor
+    #[arg(long)]
+    #[serde(default)]
+    slideshow_per_monitor: Option<bool>,
+
+    /// Video decoding, VA-API falls back to software without a render device
+    #[arg(long, value_enum)]
+    #[serde(default)]
+    video_decoder: Option<Decoder>,
+
+    /// Pause video background after this many seconds, 0 never pauses
+    #[arg(long)]
+    #[serde(default)]
+    video_pause_after: Option<u64>,
+
+    /// Pause video background while the password form is shown
+    #[arg(long)]
+    #[serde(default)]
+    video_pause_on_form: Option<bool>,
+
+    /// Size limit of pre-rendered background cache in MiB
+    #[arg(long)]
+    #[serde(default)]
+    cache_size: Option<u64>,
+
+    /// Screenshot backends to try in order
+    #[arg(long, value_enum, value_delimiter = ',')]
+    #[serde(default)]
+    screenshot_backends: Option<Vec<ScreenshotBackend>>,
+
+    /// Command printing screenshot of `{output}` to stdout, used by `command` backend
+    #[arg(long)]
+    #[serde(default)]
+    screenshot_command: Option<String>,
+
+    /// Effects applied to screenshot background in order
+    #[arg(skip)]
+    #[serde(default)]
+    effects: Option<Vec<Effect>>,
+
+    /// Path to WGSL fragment shader drawing the background
+    #[arg(long)]
+    #[serde(default)]
+    shader: Option<PathBuf>,
+
+    /// Shader frame rate, 0 renders a single frame
+    #[arg(long)]
+    #[serde(default)]
+    shader_fps: Option<u32>,
 }
 
 impl Default for Config {
@@ -62,10 +263,47 @@ impl Default for Config {
             config: default::config(),
             style: default::style(),
             background: None,
+            dark_background: None,
+            monitor_backgrounds: None,
+            layout: None,
+            widgets: None,
+            color_scheme: None,
+            day_start: None,
+            night_start: None,
+            latitude: None,
+            longitude: None,
             idle_timeout: default::idle_timeout(),
             start_hidden: default::start_hidden(),
             time_format: default::time_format(),
             date_format: default::date_format(),
+            clock_style: None,
+            clock_seconds: None,
+            time_zones: None,
+            calendars: None,
+            greeting: None,
+            greeting_variants: None,
+            power_actions: None,
+            power: None,
+            blur_method: None,
+            blur_algorithm: None,
+            blur_radius: None,
+            blur_passes: None,
+            blur_downscale: None,
+            blur_animation: None,
+            blur_animation_steps: None,
+            slideshow_interval: None,
+            slideshow_transition: None,
+            slideshow_order: None,
+            slideshow_per_monitor: None,
+            video_decoder: None,
+            video_pause_after: None,
+            video_pause_on_form: None,
+            cache_size: None,
+            screenshot_backends: None,
+            screenshot_command: None,
+            effects: None,
+            shader: None,
+            shader_fps: None,
         }
     }
 }
@@ -85,7 +323,53 @@ macro_rules! merge {
 
 impl Config {
     pub fn merge(self, other: Self) -> Self {
-        merge!(self, other, { gtk_theme, config, style, background, idle_timeout, start_hidden, time_format, date_format })
+        merge!(self, other, {
+            gtk_theme,
+            config,
+            style,
+            background,
+            dark_background,
+            monitor_backgrounds,
+            layout,
+            widgets,
+            color_scheme,
+            day_start,
+            night_start,
+            latitude,
+            longitude,
+            idle_timeout,
+            start_hidden,
+            time_format,
+            date_format,
+            clock_style,
+            clock_seconds,
+            time_zones,
+            calendars,
+            greeting,
+            greeting_variants,
+            power_actions,
+            power,
+            blur_method,
+            blur_algorithm,
+            blur_radius,
+            blur_passes,
+            blur_downscale,
+            blur_animation,
+            blur_animation_steps,
+            slideshow_interval,
+            slideshow_transition,
+            slideshow_order,
+            slideshow_per_monitor,
+            video_decoder,
+            video_pause_after,
+            video_pause_on_form,
+            cache_size,
+            screenshot_backends,
+            screenshot_command,
+            effects,
+            shader,
+            shader_fps,
+        })
     }
 
     pub const fn get_gtk_theme(&self) -> Option<&String> {
@@ -96,8 +380,57 @@ impl Config {
         self.style.clone().or_else(default::style)
     }
 
-    pub fn get_background(&self) -> Option<&Path> {
-        self.background.as_deref()
+    pub fn get_backgrounds(&self) -> Backgrounds {
+        Backgrounds {
+            default: self.background.clone().unwrap_or_default(),
+            monitors: self.monitor_backgrounds.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Switch to `dark_background` if there is one
+    pub fn use_dark_background(&mut self) {
+        if let Some(background) = self.dark_background.take() {
+            self.background = Some(background);
+        }
+    }
+
+    pub fn get_layout(&self) -> Option<&Path> {
+        self.layout.as_deref()
+    }
+
+    pub fn get_widgets(&self) -> Widgets {
+        let Some(widgets) = &self.widgets else {
+            return Widgets::default();
+        };
+
+        Widgets(
+            widgets
+                .iter()
+                .copied()
+                .filter(|kind| {
+                    kind.is_available() || {
+                        log::warning!("widget {kind:?} is not available in this build");
+                        false
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    pub fn get_color_scheme(&self) -> ColorScheme {
+        self.color_scheme.unwrap_or_default()
+    }
+
+    pub fn get_day_start(&self) -> &str {
+        self.day_start.as_deref().unwrap_or(default::DAY_START)
+    }
+
+    pub fn get_night_start(&self) -> &str {
+        self.night_start.as_deref().unwrap_or(default::NIGHT_START)
+    }
+
+    pub fn get_coordinates(&self) -> Option<(f64, f64)> {
+        self.latitude.zip(self.longitude)
     }
 
     pub fn get_config(&self) -> Option<PathBuf> {
@@ -119,16 +452,178 @@ impl Config {
     pub fn get_date_format(&self) -> &str {
         self.date_format.as_deref().unwrap_or(default::DATE_FORMAT)
     }
+
+    pub fn get_clock_style(&self) -> ClockStyle {
+        self.clock_style.unwrap_or_default()
+    }
+
+    pub fn get_clock_seconds(&self) -> bool {
+        self.clock_seconds.unwrap_or(default::CLOCK_SECONDS)
+    }
+
+    pub fn get_time_zones(&self) -> ZoneClocks {
+        ZoneClocks(self.time_zones.clone().unwrap_or_default())
+    }
+
+    pub fn get_calendars(&self) -> Calendars {
+        Calendars(self.calendars.clone().unwrap_or_default())
+    }
+
+    pub fn get_power_actions(&self) -> PowerActions {
+        PowerActions {
+            actions: self
+                .power_actions
+                .clone()
+                .unwrap_or_else(|| default::POWER_ACTIONS.to_vec()),
+            configs: self.power.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn get_greeting(&self) -> Templates {
+        Templates {
+            default: self.greeting.clone(),
+            variants: self.greeting_variants.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn get_blur(&self) -> BlurParams {
+        BlurParams {
+            method: self.blur_method.unwrap_or_default(),
+            algorithm: self.blur_algorithm.unwrap_or_default(),
+            radius: self.blur_radius.unwrap_or(default::BLUR_RADIUS),
+            passes: self.blur_passes.unwrap_or(default::BLUR_PASSES),
+            downscale: self
+                .blur_downscale
+                .unwrap_or(default::BLUR_DOWNSCALE)
+                .max(1),
+        }
+    }
+
+    pub fn get_blur_animation(&self) -> u32 {
+        self.blur_animation.unwrap_or(default::BLUR_ANIMATION)
+    }
+
+    pub fn get_blur_animation_steps(&self) -> u32 {
+        self.blur_animation_steps
+            .unwrap_or(default::BLUR_ANIMATION_STEPS)
+            .max(1)
+    }
+
+    pub fn get_slideshow(&self) -> SlideshowParams {
+        SlideshowParams {
+            interval: self
+                .slideshow_interval
+                .unwrap_or(default::SLIDESHOW_INTERVAL),
+            transition: self
+                .slideshow_transition
+                .unwrap_or(default::SLIDESHOW_TRANSITION),
+            order: self.slideshow_order.unwrap_or_default(),
+            per_monitor: self
+                .slideshow_per_monitor
+                .unwrap_or(default::SLIDESHOW_PER_MONITOR),
+        }
+    }
+
+    pub fn get_video_decoder(&self) -> Decoder {
+        self.video_decoder.unwrap_or_default()
+    }
+
+    pub fn get_video_pause_after(&self) -> u64 {
+        self.video_pause_after.unwrap_or(default::VIDEO_PAUSE_AFTER)
+    }
+
+    pub fn get_video_pause_on_form(&self) -> bool {
+        self.video_pause_on_form
+            .unwrap_or(default::VIDEO_PAUSE_ON_FORM)
+    }
+
+    pub fn get_cache_size(&self) -> u64 {
+        self.cache_size.unwrap_or(default::CACHE_SIZE)
+    }
+
+    pub fn get_screenshot_backends(&self) -> &[ScreenshotBackend] {
+        self.screenshot_backends
+            .as_deref()
+            .unwrap_or(default::SCREENSHOT_BACKENDS)
+    }
+
+    pub fn get_screenshot_command(&self) -> &str {
+        self.screenshot_command
+            .as_deref()
+            .unwrap_or(default::SCREENSHOT_COMMAND)
+    }
+
+    pub fn get_effects(&self) -> Effects {
+        self.effects.clone().map(Effects).unwrap_or_default()
+    }
+
+    pub fn get_shader(&self) -> Option<&Path> {
+        self.shader.as_deref()
+    }
+
+    /// Fall back to plain background, e.g. if shader failed to compile
+    pub fn clear_shader(&mut self) {
+        self.shader = None;
+    }
+
+    pub fn get_shader_fps(&self) -> u32 {
+        self.shader_fps.unwrap_or(default::SHADER_FPS)
+    }
 }
 
 pub mod default {
     pub const TIME_FORMAT: &str = "%H:%M";
     pub const DATE_FORMAT: &str = "%A, %d %B %Y";
     pub const START_HIDDEN: bool = false;
+    pub const CLOCK_SECONDS: bool = true;
     pub const IDLE_TIMEOUT: u64 = 30;
+    pub const BLUR_RADIUS: u32 = 16;
+    pub const BLUR_PASSES: u32 = 3;
+    pub const BLUR_DOWNSCALE: u32 = 1;
+    pub const BLUR_ANIMATION: u32 = 400;
+    pub const BLUR_ANIMATION_STEPS: u32 = 8;
+    pub const SHADER_FPS: u32 = 0;
+    pub const SLIDESHOW_INTERVAL: u64 = 300;
+    pub const SLIDESHOW_TRANSITION: u32 = 1000;
+    pub const SLIDESHOW_PER_MONITOR: bool = false;
+    pub const VIDEO_PAUSE_AFTER: u64 = 0;
+    pub const VIDEO_PAUSE_ON_FORM: bool = false;
+    pub const CACHE_SIZE: u64 = 256;
+    pub const DAY_START: &str = "07:00";
+    pub const NIGHT_START: &str = "19:00";
+    pub const SCREENSHOT_BACKENDS: &[ScreenshotBackend] = &[
+        ScreenshotBackend::Wlr,
+        ScreenshotBackend::Ext,
+        ScreenshotBackend::Xcap,
+        ScreenshotBackend::Command,
+    ];
+    pub const SCREENSHOT_COMMAND: &str = "grim -o {output} -";
+    pub const POWER_COUNTDOWN: u32 = 5;
+    pub const POWER_ACTIONS: &[PowerAction] = &[
+        PowerAction::PowerOff,
+        PowerAction::Reboot,
+        PowerAction::Suspend,
+    ];
+    pub const WIDGETS: &[WidgetKind] = &[
+        WidgetKind::Player,
+        WidgetKind::Network,
+        WidgetKind::Battery,
+        WidgetKind::KeyboardLayout,
+        WidgetKind::Userinfo,
+        WidgetKind::Greeting,
+        WidgetKind::Clock,
+        WidgetKind::Events,
+        WidgetKind::LockedSince,
+        WidgetKind::Messages,
+        WidgetKind::SubmitButton,
+        WidgetKind::CapsLock,
+        WidgetKind::Powerbar,
+    ];
 
     use std::path::PathBuf;
 
+    use super::{PowerAction, ScreenshotBackend, WidgetKind};
+
     pub fn config() -> Option<PathBuf> {
         xdg::BaseDirectories::with_prefix("waylock").get_config_file("config.toml")
     }
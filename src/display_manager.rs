@@ -0,0 +1,81 @@
+use gtk::{gio, glib, prelude::*};
+
+const LIGHTDM: &str = "org.freedesktop.DisplayManager";
+const LIGHTDM_SEAT: &str = "org.freedesktop.DisplayManager.Seat";
+const LIGHTDM_DEFAULT_SEAT_PATH: &str = "/org/freedesktop/DisplayManager/Seat0";
+
+const GDM: &str = "org.gnome.DisplayManager";
+const GDM_PATH: &str = "/org/gnome/DisplayManager/LocalDisplayFactory";
+const GDM_FACTORY: &str = "org.gnome.DisplayManager.LocalDisplayFactory";
+
+/// Display manager able to show a greeter for another user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisplayManager {
+    /// LightDM, or another implementation of its seat interface
+    LightDm {
+        seat: String,
+    },
+    Gdm,
+}
+
+async fn has_owner(connection: &gio::DBusConnection, name: &str) -> bool {
+    connection
+        .call_future(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameHasOwner",
+            Some(&(name,).to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await
+        .ok()
+        .and_then(|reply| reply.child_value(0).get::<bool>())
+        .unwrap_or(false)
+}
+
+/// Running display manager supporting user switching, if any
+pub async fn find() -> Option<DisplayManager> {
+    let connection = gio::bus_get_future(gio::BusType::System).await.ok()?;
+
+    if has_owner(&connection, LIGHTDM).await {
+        // LightDM exports the seat of the session to it
+        let seat =
+            std::env::var("XDG_SEAT_PATH").unwrap_or_else(|_| LIGHTDM_DEFAULT_SEAT_PATH.into());
+        return Some(DisplayManager::LightDm { seat });
+    }
+
+    if has_owner(&connection, GDM).await {
+        return Some(DisplayManager::Gdm);
+    }
+
+    None
+}
+
+/// Show the greeter, the current session stays locked behind it
+pub async fn switch_to_greeter(manager: &DisplayManager) -> Result<(), glib::Error> {
+    let connection = gio::bus_get_future(gio::BusType::System).await?;
+
+    let (destination, path, interface, method) = match manager {
+        DisplayManager::LightDm { seat } => {
+            (LIGHTDM, seat.as_str(), LIGHTDM_SEAT, "SwitchToGreeter")
+        }
+        DisplayManager::Gdm => (GDM, GDM_PATH, GDM_FACTORY, "CreateTransientDisplay"),
+    };
+
+    connection
+        .call_future(
+            Some(destination),
+            path,
+            interface,
+            method,
+            None,
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await
+        .map(drop)
+}
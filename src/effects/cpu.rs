@@ -0,0 +1,127 @@
+use super::Pass;
+
+/// Same weights as in `effects.wgsl`
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+fn to_unorm(c: u8) -> f32 {
+    f32::from(c) / 255.0
+}
+
+fn from_unorm(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Call `f` for every pixel with its coordinates and RGB normalized to 0..1
+fn for_each_pixel(image: &mut blur::Image, mut f: impl FnMut(u32, u32, &mut [f32; 3])) {
+    let width = image.width;
+    let data: &mut [u8] = bytemuck::cast_slice_mut(&mut image.pixels);
+
+    for (i, pixel) in data.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let mut rgb = [to_unorm(pixel[0]), to_unorm(pixel[1]), to_unorm(pixel[2])];
+
+        f(x, y, &mut rgb);
+
+        for c in 0..3 {
+            pixel[c] = from_unorm(rgb[c]);
+        }
+    }
+}
+
+fn pixelate(image: &mut blur::Image, size: u32) {
+    let (width, height) = (image.width, image.height);
+    let data: &mut [u8] = bytemuck::cast_slice_mut(&mut image.pixels);
+
+    for by in (0..height).step_by(size as usize) {
+        for bx in (0..width).step_by(size as usize) {
+            let rows = by..(by + size).min(height);
+            let cols = bx..(bx + size).min(width);
+            let count = (rows.len() * cols.len()) as f32;
+            let mut sum = [0f32; 4];
+
+            for y in rows.clone() {
+                for x in cols.clone() {
+                    let i = ((y * width + x) * 4) as usize;
+                    for c in 0..4 {
+                        sum[c] += to_unorm(data[i + c]);
+                    }
+                }
+            }
+
+            for y in rows.clone() {
+                for x in cols.clone() {
+                    let i = ((y * width + x) * 4) as usize;
+                    for c in 0..4 {
+                        data[i + c] = from_unorm(sum[c] / count);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn overlay(image: &mut blur::Image, overlay: &blur::Image, opacity: f32) {
+    let (width, height) = (image.width as i64, image.height as i64);
+    let (ow, oh) = (overlay.width as i64, overlay.height as i64);
+    let (ox, oy) = ((width - ow) / 2, (height - oh) / 2);
+    let src: &[u8] = bytemuck::cast_slice(&overlay.pixels);
+    let data: &mut [u8] = bytemuck::cast_slice_mut(&mut image.pixels);
+
+    for y in oy.max(0)..(oy + oh).min(height) {
+        for x in ox.max(0)..(ox + ow).min(width) {
+            let o = (((y - oy) * ow + (x - ox)) * 4) as usize;
+            let i = ((y * width + x) * 4) as usize;
+            let alpha = to_unorm(src[o + 3]) * opacity;
+
+            for c in 0..3 {
+                let (a, b) = (to_unorm(data[i + c]), to_unorm(src[o + c]));
+                data[i + c] = from_unorm(a + (b - a) * alpha);
+            }
+        }
+    }
+}
+
+pub(super) fn apply(image: &mut blur::Image, pass: &Pass) {
+    match *pass {
+        Pass::Brightness(factor) => for_each_pixel(image, |_, _, rgb| {
+            for c in rgb.iter_mut() {
+                *c *= factor;
+            }
+        }),
+        Pass::Desaturate(amount) => for_each_pixel(image, |_, _, rgb| {
+            let gray = rgb[0] * LUMA[0] + rgb[1] * LUMA[1] + rgb[2] * LUMA[2];
+            for c in rgb.iter_mut() {
+                *c += (gray - *c) * amount;
+            }
+        }),
+        Pass::Pixelate(size) => pixelate(image, size),
+        Pass::Vignette { strength, radius } => {
+            let (cx, cy) = (image.width as f32 / 2.0, image.height as f32 / 2.0);
+
+            for_each_pixel(image, |x, y, rgb| {
+                let dx = (x as f32 + 0.5 - cx) / cx;
+                let dy = (y as f32 + 0.5 - cy) / cy;
+                let d = (dx * dx + dy * dy).sqrt() / std::f32::consts::SQRT_2;
+                let factor = 1.0 - strength * smoothstep(radius, 1.0, d);
+
+                for c in rgb.iter_mut() {
+                    *c *= factor;
+                }
+            });
+        }
+        Pass::Tint { color, amount } => for_each_pixel(image, |_, _, rgb| {
+            for (c, t) in rgb.iter_mut().zip(color) {
+                *c += (t - *c) * amount * color[3];
+            }
+        }),
+        Pass::Overlay {
+            image: ref src,
+            opacity,
+        } => overlay(image, src, opacity),
+    }
+}
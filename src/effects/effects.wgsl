@@ -0,0 +1,102 @@
+// GPU counterpart of `cpu.rs`, pixels are RGBA8 packed into u32.
+
+const BRIGHTNESS: u32 = 0u;
+const DESATURATE: u32 = 1u;
+const PIXELATE: u32 = 2u;
+const VIGNETTE: u32 = 3u;
+const TINT: u32 = 4u;
+const OVERLAY: u32 = 5u;
+
+const LUMA: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
+
+struct Params {
+    mode: u32,
+    width: u32,
+    height: u32,
+    size: u32,
+    amount: f32,
+    radius: f32,
+    overlay_width: u32,
+    overlay_height: u32,
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> src: array<u32>;
+@group(0) @binding(2) var<storage, read_write> dst: array<u32>;
+@group(0) @binding(3) var<storage, read> overlay: array<u32>;
+
+fn load(x: u32, y: u32) -> vec4<f32> {
+    return unpack4x8unorm(src[y * params.width + x]);
+}
+
+fn pixelate(x: u32, y: u32) -> vec4<f32> {
+    let bx = x / params.size * params.size;
+    let by = y / params.size * params.size;
+    let ex = min(bx + params.size, params.width);
+    let ey = min(by + params.size, params.height);
+
+    var sum = vec4<f32>(0.0);
+    for (var j = by; j < ey; j++) {
+        for (var i = bx; i < ex; i++) {
+            sum += load(i, j);
+        }
+    }
+
+    return sum / f32((ex - bx) * (ey - by));
+}
+
+fn vignette(x: u32, y: u32, color: vec4<f32>) -> vec4<f32> {
+    let center = vec2<f32>(f32(params.width), f32(params.height)) * 0.5;
+    let d = length((vec2<f32>(f32(x), f32(y)) + 0.5 - center) / center) / sqrt(2.0);
+    let factor = 1.0 - params.amount * smoothstep(params.radius, 1.0, d);
+
+    return vec4<f32>(color.rgb * factor, color.a);
+}
+
+fn blend_overlay(x: u32, y: u32, color: vec4<f32>) -> vec4<f32> {
+    let origin = (vec2<i32>(i32(params.width), i32(params.height))
+        - vec2<i32>(i32(params.overlay_width), i32(params.overlay_height))) / 2;
+    let p = vec2<i32>(i32(x), i32(y)) - origin;
+
+    if (p.x < 0 || p.y < 0 || p.x >= i32(params.overlay_width) || p.y >= i32(params.overlay_height)) {
+        return color;
+    }
+
+    let o = unpack4x8unorm(overlay[u32(p.y) * params.overlay_width + u32(p.x)]);
+
+    return vec4<f32>(mix(color.rgb, o.rgb, o.a * params.amount), color.a);
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.width || id.y >= params.height) {
+        return;
+    }
+
+    var color = load(id.x, id.y);
+
+    switch params.mode {
+        case BRIGHTNESS: {
+            color = vec4<f32>(color.rgb * params.amount, color.a);
+        }
+        case DESATURATE: {
+            color = vec4<f32>(mix(color.rgb, vec3<f32>(dot(color.rgb, LUMA)), params.amount), color.a);
+        }
+        case PIXELATE: {
+            color = pixelate(id.x, id.y);
+        }
+        case VIGNETTE: {
+            color = vignette(id.x, id.y, color);
+        }
+        case TINT: {
+            color = vec4<f32>(mix(color.rgb, params.color.rgb, params.amount * params.color.a), color.a);
+        }
+        case OVERLAY: {
+            color = blend_overlay(id.x, id.y, color);
+        }
+        default: {}
+    }
+
+    dst[id.y * params.width + id.x] = pack4x8unorm(color);
+}
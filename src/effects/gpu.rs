@@ -0,0 +1,193 @@
+use futures::executor::block_on;
+use wgpu::util::DeviceExt as _;
+
+use super::Pass;
+use crate::log;
+
+const WORKGROUP_SIZE: u32 = 8;
+
+pub struct Context {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Context {
+    pub fn new() -> Option<Self> {
+        let (adapter, device, queue) = crate::gpu::request_device("effects")
+            .map_err(|err| log::info!("{err}"))
+            .ok()?;
+
+        if !adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        {
+            log::info!("GPU adapter does not support compute shaders");
+            return None;
+        }
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = device.create_shader_module(wgpu::include_wgsl!("effects.wgsl"));
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("effects"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        if let Some(err) = block_on(device.pop_error_scope()) {
+            log::warning!("failed to create effects pipeline: {err}");
+            return None;
+        }
+
+        Some(Self {
+            device,
+            queue,
+            pipeline,
+        })
+    }
+
+    /// Uniform block matching `Params` in `effects.wgsl`
+    fn params(image: &blur::Image, pass: &Pass) -> [u32; 12] {
+        let (mode, size, amount, radius, color, overlay) = match *pass {
+            Pass::Brightness(factor) => (0, 0, factor, 0.0, [0.0; 4], (0, 0)),
+            Pass::Desaturate(amount) => (1, 0, amount, 0.0, [0.0; 4], (0, 0)),
+            Pass::Pixelate(size) => (2, size, 0.0, 0.0, [0.0; 4], (0, 0)),
+            Pass::Vignette { strength, radius } => (3, 0, strength, radius, [0.0; 4], (0, 0)),
+            Pass::Tint { color, amount } => (4, 0, amount, 0.0, color, (0, 0)),
+            Pass::Overlay { ref image, opacity } => {
+                (5, 0, opacity, 0.0, [0.0; 4], (image.width, image.height))
+            }
+        };
+
+        [
+            mode,
+            image.width,
+            image.height,
+            size,
+            amount.to_bits(),
+            radius.to_bits(),
+            overlay.0,
+            overlay.1,
+            color[0].to_bits(),
+            color[1].to_bits(),
+            color[2].to_bits(),
+            color[3].to_bits(),
+        ]
+    }
+
+    /// Run pass on the image in place, `None` if GPU could not do it
+    pub fn apply(&self, image: &mut blur::Image, pass: &Pass) -> Option<()> {
+        let device = &self.device;
+        let data: &[u8] = bytemuck::cast_slice(&image.pixels);
+        let size = data.len() as wgpu::BufferAddress;
+
+        if size > u64::from(device.limits().max_storage_buffer_binding_size) {
+            log::info!("image is too large for GPU storage buffer");
+            return None;
+        }
+
+        let overlay: &[u8] = match pass {
+            Pass::Overlay { image, .. } => bytemuck::cast_slice(&image.pixels),
+            // Binding must not be empty
+            _ => &[0; 4],
+        };
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("effect params"),
+            contents: bytemuck::cast_slice(&Self::params(image, pass)),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let src = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("effect source"),
+            contents: data,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let overlay = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("effect overlay"),
+            contents: overlay,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let dst = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("effect destination"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("effects"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: src.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: dst.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: overlay.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+
+        {
+            let mut compute = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            compute.set_pipeline(&self.pipeline);
+            compute.set_bind_group(0, &bind_group, &[]);
+            compute.dispatch_workgroups(
+                image.width.div_ceil(WORKGROUP_SIZE),
+                image.height.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+
+        encoder.copy_buffer_to_buffer(&dst, 0, &readback, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        if let Some(err) = block_on(device.pop_error_scope()) {
+            log::warning!("failed to run effect: {err}");
+            return None;
+        }
+
+        let slice = readback.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+
+        if let Err(err) = device.poll(wgpu::PollType::wait_indefinitely()) {
+            log::warning!("failed to wait for GPU: {err}");
+            return None;
+        }
+
+        rx.recv().ok()?.ok()?;
+
+        let mapped = slice.get_mapped_range();
+        bytemuck::cast_slice_mut::<_, u8>(&mut image.pixels).copy_from_slice(&mapped);
+        drop(mapped);
+        readback.unmap();
+
+        Some(())
+    }
+}
@@ -0,0 +1,339 @@
+mod cpu;
+#[cfg(feature = "gpu")]
+mod gpu;
+#[cfg(test)]
+mod tests;
+
+use std::path::PathBuf;
+
+use gtk::{gdk, glib};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "gpu")]
+use crate::blur::{BlurMethod, resolve_method};
+use crate::{
+    blur::{BlurParams, blur_image, from_texture, resize_cover, to_texture},
+    log,
+};
+
+/// Effect applied to screenshot background, effects are applied in order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Effect {
+    /// Blur with parameters from `blur-*` options
+    Blur,
+    /// Multiply color by factor, values below 1 darken the image
+    #[serde(alias = "dim")]
+    Brightness { factor: f32 },
+    /// Mix with grayscale, 1 is fully gray
+    #[serde(alias = "grayscale")]
+    Desaturate {
+        #[serde(default = "default::amount")]
+        amount: f32,
+    },
+    /// Replace each block of `size`x`size` pixels with its average
+    Pixelate { size: u32 },
+    /// Darken edges starting at `radius` (0 is center, 1 is corner)
+    Vignette {
+        #[serde(default = "default::vignette_strength")]
+        strength: f32,
+        #[serde(default = "default::vignette_radius")]
+        radius: f32,
+    },
+    /// Mix with a CSS color
+    Tint {
+        color: String,
+        #[serde(default = "default::tint_amount")]
+        amount: f32,
+    },
+    /// Draw image at the center of the screen
+    Overlay {
+        path: PathBuf,
+        #[serde(default = "default::amount")]
+        opacity: f32,
+    },
+}
+
+mod default {
+    pub const fn amount() -> f32 {
+        1.0
+    }
+
+    pub const fn vignette_strength() -> f32 {
+        0.5
+    }
+
+    pub const fn vignette_radius() -> f32 {
+        0.5
+    }
+
+    pub const fn tint_amount() -> f32 {
+        0.3
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, glib::Boxed)]
+#[boxed_type(name = "WaylockEffects")]
+pub struct Effects(pub Vec<Effect>);
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self(vec![Effect::Blur])
+    }
+}
+
+/// Effect with its parameters resolved and ready to be executed on CPU or GPU
+enum Pass {
+    Brightness(f32),
+    Desaturate(f32),
+    Pixelate(u32),
+    Vignette { strength: f32, radius: f32 },
+    Tint { color: [f32; 4], amount: f32 },
+    Overlay { image: blur::Image, opacity: f32 },
+}
+
+impl Pass {
+    fn new(effect: &Effect) -> Option<Self> {
+        Some(match effect {
+            Effect::Blur => return None,
+            Effect::Brightness { factor } => Self::Brightness(factor.max(0.0)),
+            Effect::Desaturate { amount } => Self::Desaturate(amount.clamp(0.0, 1.0)),
+            Effect::Pixelate { size } => Self::Pixelate((*size).max(1)),
+            Effect::Vignette { strength, radius } => Self::Vignette {
+                strength: strength.clamp(0.0, 1.0),
+                radius: radius.clamp(0.0, 0.99),
+            },
+            Effect::Tint { color, amount } => {
+                let rgba = gdk::RGBA::parse(color.as_str())
+                    .map_err(|err| log::warning!("invalid tint color {color:?}: {err}"))
+                    .ok()?;
+
+                Self::Tint {
+                    color: [rgba.red(), rgba.green(), rgba.blue(), rgba.alpha()],
+                    amount: amount.clamp(0.0, 1.0),
+                }
+            }
+            Effect::Overlay { path, opacity } => Self::Overlay {
+                image: load_image(path)?,
+                opacity: opacity.clamp(0.0, 1.0),
+            },
+        })
+    }
+
+    /// Sizes are given in output pixels, `scale` output pixels make one of a
+    /// downscaled image. `None` if the pass does not depend on it.
+    fn scaled(&self, scale: f32) -> Option<Self> {
+        if scale == 1.0 {
+            return None;
+        }
+
+        match *self {
+            Self::Pixelate(size) => Some(Self::Pixelate(
+                ((size as f32 / scale).round() as u32).max(1),
+            )),
+            Self::Overlay { ref image, opacity } => Some(Self::Overlay {
+                image: resize_cover(
+                    image,
+                    ((image.width as f32 / scale).round() as u32).max(1),
+                    ((image.height as f32 / scale).round() as u32).max(1),
+                ),
+                opacity,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Load image file as RGBA
+fn load_image(path: &std::path::Path) -> Option<blur::Image> {
+    gdk::Texture::from_filename(path)
+        .map_err(|err| log::warning!("failed to load image {path:?}: {err}"))
+        .ok()
+        .map(|texture| from_texture(&texture))
+}
+
+enum Step {
+    Blur,
+    Pass(Pass),
+}
+
+/// Effects ready to be applied, possibly many times: overlay images are
+/// loaded and GPU context is created once
+pub struct Pipeline {
+    steps: Vec<Step>,
+    #[cfg(feature = "gpu")]
+    context: Option<gpu::Context>,
+}
+
+impl Pipeline {
+    /// GPU is used if blur method of `params` resolves to it
+    #[cfg_attr(not(feature = "gpu"), allow(unused_variables))]
+    pub fn new(effects: &[Effect], params: &BlurParams) -> Self {
+        let steps = effects
+            .iter()
+            .filter_map(|effect| match effect {
+                Effect::Blur => Some(Step::Blur),
+                effect => Pass::new(effect).map(Step::Pass),
+            })
+            .collect::<Vec<_>>();
+
+        #[cfg(feature = "gpu")]
+        let context = (resolve_method(params) == BlurMethod::GPU
+            && steps.iter().any(|step| matches!(step, Step::Pass(_))))
+        .then(gpu::Context::new)
+        .flatten();
+
+        Self {
+            steps,
+            #[cfg(feature = "gpu")]
+            context,
+        }
+    }
+
+    /// Apply effects to image in place, `params` may differ from the ones
+    /// the pipeline was made with, e.g. by radius
+    pub fn apply(&self, image: &mut blur::Image, params: &BlurParams) {
+        let width = image.width;
+        self.run(&self.steps, image, params, width);
+    }
+
+    /// `width` is the one of the output, passes sized in its pixels are scaled
+    /// if the image was downscaled by a blur
+    fn run(&self, steps: &[Step], image: &mut blur::Image, params: &BlurParams, width: u32) {
+        for step in steps {
+            let pass = match step {
+                Step::Blur => {
+                    blur_image(image, params);
+                    continue;
+                }
+                Step::Pass(pass) => pass,
+            };
+
+            let scaled = pass.scaled(width as f32 / image.width as f32);
+            let pass = scaled.as_ref().unwrap_or(pass);
+
+            #[cfg(feature = "gpu")]
+            if let Some(ref context) = self.context {
+                if context.apply(image, pass).is_some() {
+                    continue;
+                }
+
+                log::warning!("failed to apply effect on GPU, fallback to CPU");
+            }
+
+            cpu::apply(image, pass);
+        }
+    }
+
+    pub fn has_blur(&self) -> bool {
+        self.steps.iter().any(|step| matches!(step, Step::Blur))
+    }
+
+    /// Apply effects and make texture of the result
+    pub fn render(&self, image: &mut blur::Image, params: &BlurParams) -> gdk::MemoryTexture {
+        self.apply(image, params);
+        to_texture(image)
+    }
+
+    #[cfg(all(test, feature = "gpu"))]
+    fn uses_gpu(&self) -> bool {
+        self.context.is_some()
+    }
+
+    #[cfg(all(test, not(feature = "gpu")))]
+    fn uses_gpu(&self) -> bool {
+        false
+    }
+}
+
+/// Blur growing frame by frame. Each frame blurs the previous one a bit more
+/// instead of blurring the sharp image again with a larger radius. Effects
+/// before the first blur are applied once, the ones after it to every frame.
+pub struct Progressive<'a> {
+    pipeline: &'a Pipeline,
+    params: BlurParams,
+    /// Index of the first blur in `pipeline`
+    split: usize,
+    /// Image blurred so far
+    image: blur::Image,
+    /// Of the output, `image` gets narrower once downscaled
+    width: u32,
+    /// Radius reached so far, in output pixels
+    radius: f32,
+}
+
+impl<'a> Progressive<'a> {
+    pub fn new(pipeline: &'a Pipeline, mut image: blur::Image, params: &BlurParams) -> Self {
+        let split = pipeline
+            .steps
+            .iter()
+            .position(|step| matches!(step, Step::Blur))
+            .unwrap_or(pipeline.steps.len());
+        let width = image.width;
+
+        pipeline.run(&pipeline.steps[..split], &mut image, params, width);
+
+        Self {
+            pipeline,
+            params: *params,
+            split,
+            image,
+            width,
+            radius: 0.0,
+        }
+    }
+
+    /// Frame with the first blur grown to `radius`
+    pub fn frame(&mut self, radius: u32) -> blur::Image {
+        let radius = radius as f32;
+
+        if self.split < self.pipeline.steps.len() && radius > self.radius {
+            // Gaussian blurs add up by squares of their radii
+            let increment = (radius * radius - self.radius * self.radius).sqrt();
+
+            let params = if self.image.width == self.width {
+                BlurParams {
+                    radius: (increment.round() as u32).max(1),
+                    ..self.params
+                }
+            } else {
+                // Already downscaled by the first frame
+                let factor = self.width as f32 / self.image.width as f32;
+
+                BlurParams {
+                    radius: ((increment / factor).round() as u32).max(1),
+                    downscale: 1,
+                    ..self.params
+                }
+            };
+
+            blur_image(&mut self.image, &params);
+            self.radius = radius;
+        }
+
+        let mut frame = blur::Image {
+            width: self.image.width,
+            height: self.image.height,
+            pixels: self.image.pixels.clone(),
+        };
+
+        let rest = self
+            .pipeline
+            .steps
+            .get(self.split + 1..)
+            .unwrap_or_default();
+        self.pipeline
+            .run(rest, &mut frame, &self.params, self.width);
+
+        frame
+    }
+}
+
+/// Apply effects once and make texture of the result
+pub fn render(
+    image: &mut blur::Image,
+    effects: &[Effect],
+    params: &BlurParams,
+) -> gdk::MemoryTexture {
+    Pipeline::new(effects, params).render(image, params)
+}
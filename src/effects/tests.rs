@@ -0,0 +1,232 @@
+// Golden image tests: effects are applied to a small generated image and
+// compared to references in `tests/golden/effects`. Run with `WAYLOCK_BLESS=1`
+// to write CPU results as new references after an intended change.
+
+use std::path::PathBuf;
+
+use gtk::{gdk, prelude::*};
+
+use super::{Effect, Pass, Pipeline};
+use crate::blur::{BlurAlgorithm, BlurMethod, BlurParams, from_texture, to_texture};
+
+const WIDTH: u32 = 32;
+const HEIGHT: u32 = 24;
+
+/// Rounding differs a bit between CPU and GPU
+const TOLERANCE: u8 = 2;
+/// libblur and GPU blur approximate gaussian in their own ways
+const BLUR_TOLERANCE: u8 = 24;
+
+fn golden(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden/effects")
+        .join(name)
+        .with_extension("png")
+}
+
+/// Red and green ramps with a blue checkerboard of 8x8 cells
+fn fixture() -> blur::Image {
+    let mut pixels = Vec::with_capacity((WIDTH * HEIGHT * 4) as usize);
+
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let blue = if (x / 8 + y / 8) % 2 == 1 { 255 } else { 0 };
+            pixels.extend([(x * 8) as u8, (y * 10) as u8, blue, 255]);
+        }
+    }
+
+    blur::Image {
+        width: WIDTH,
+        height: HEIGHT,
+        pixels: bytemuck::cast_vec(pixels),
+    }
+}
+
+fn params(method: BlurMethod) -> BlurParams {
+    BlurParams {
+        method,
+        algorithm: BlurAlgorithm::Gaussian,
+        radius: 3,
+        passes: 1,
+        downscale: 1,
+    }
+}
+
+#[cfg(feature = "gpu")]
+fn gpu_available() -> bool {
+    crate::gpu::available()
+}
+
+#[cfg(not(feature = "gpu"))]
+fn gpu_available() -> bool {
+    false
+}
+
+fn compare(name: &str, image: &blur::Image, tolerance: u8) {
+    let path = golden(name);
+
+    if std::env::var_os("WAYLOCK_BLESS").is_some() {
+        to_texture(image).save_to_png(&path).unwrap();
+        return;
+    }
+
+    let texture = gdk::Texture::from_filename(&path)
+        .unwrap_or_else(|err| panic!("failed to load {path:?}: {err}"));
+    let expected = from_texture(&texture);
+
+    assert_eq!(
+        (image.width, image.height),
+        (expected.width, expected.height),
+        "{name}: size differs"
+    );
+
+    let actual: &[u8] = bytemuck::cast_slice(&image.pixels);
+    let expected: &[u8] = bytemuck::cast_slice(&expected.pixels);
+    let worst = actual
+        .iter()
+        .zip(expected)
+        .map(|(a, b)| a.abs_diff(*b))
+        .max()
+        .unwrap_or(0);
+
+    assert!(
+        worst <= tolerance,
+        "{name}: differs from {path:?} by {worst}, more than {tolerance}"
+    );
+}
+
+/// Render `effects` on CPU and, if there is an adapter, on GPU
+fn check(name: &str, effects: &[Effect], tolerance: u8) {
+    let pipeline = Pipeline::new(effects, &params(BlurMethod::CPU));
+    let mut image = fixture();
+    pipeline.apply(&mut image, &params(BlurMethod::CPU));
+    compare(name, &image, tolerance);
+
+    if std::env::var_os("WAYLOCK_BLESS").is_some() {
+        return;
+    }
+
+    if !gpu_available() {
+        eprintln!("{name}: no GPU adapter, GPU path is not checked");
+        return;
+    }
+
+    let pipeline = Pipeline::new(effects, &params(BlurMethod::GPU));
+    if effects != [Effect::Blur] && !pipeline.uses_gpu() {
+        eprintln!("{name}: GPU can not run effects, GPU path is not checked");
+        return;
+    }
+
+    let mut image = fixture();
+    pipeline.apply(&mut image, &params(BlurMethod::GPU));
+    compare(name, &image, tolerance);
+}
+
+#[test]
+fn blur() {
+    check("blur", &[Effect::Blur], BLUR_TOLERANCE);
+}
+
+#[test]
+fn brightness() {
+    check(
+        "brightness",
+        &[Effect::Brightness { factor: 0.6 }],
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn desaturate() {
+    check(
+        "desaturate",
+        &[Effect::Desaturate { amount: 0.7 }],
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn vignette() {
+    check(
+        "vignette",
+        &[Effect::Vignette {
+            strength: 0.8,
+            radius: 0.3,
+        }],
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn pixelate() {
+    check("pixelate", &[Effect::Pixelate { size: 5 }], TOLERANCE);
+}
+
+#[test]
+fn tint() {
+    check(
+        "tint",
+        &[Effect::Tint {
+            color: "#3366ff".into(),
+            amount: 0.4,
+        }],
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn overlay() {
+    check(
+        "overlay",
+        &[Effect::Overlay {
+            path: golden("overlay-source"),
+            opacity: 0.75,
+        }],
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn sizes_follow_downscale() {
+    assert!(matches!(
+        Pass::Pixelate(8).scaled(2.0),
+        Some(Pass::Pixelate(4))
+    ));
+    assert!(matches!(
+        Pass::Pixelate(1).scaled(4.0),
+        Some(Pass::Pixelate(1))
+    ));
+    assert!(Pass::Pixelate(8).scaled(1.0).is_none());
+
+    let Some(Pass::Overlay { image, .. }) = Pass::new(&Effect::Overlay {
+        path: golden("overlay-source"),
+        opacity: 1.0,
+    })
+    .and_then(|pass| pass.scaled(2.0)) else {
+        panic!("overlay is not scaled");
+    };
+
+    assert_eq!((image.width, image.height), (4, 3));
+}
+
+#[test]
+fn pixelate_after_downscaled_blur() {
+    let params = BlurParams {
+        downscale: 2,
+        ..params(BlurMethod::CPU)
+    };
+    let pipeline = Pipeline::new(&[Effect::Blur, Effect::Pixelate { size: 8 }], &params);
+    let mut image = fixture();
+    pipeline.apply(&mut image, &params);
+
+    assert_eq!((image.width, image.height), (WIDTH / 2, HEIGHT / 2));
+
+    // 8 output pixels are 4 of the downscaled image, so each 4x4 block is flat
+    let pixels: &[[u8; 4]] = bytemuck::cast_slice(&image.pixels);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let corner = pixels[((y / 4 * 4) * image.width + x / 4 * 4) as usize];
+            assert_eq!(pixels[(y * image.width + x) as usize], corner, "at {x}x{y}");
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::OnceLock;
+
+use futures::executor::block_on;
+
+use crate::log;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("no GPU adapter available: {0}")]
+    Adapter(#[from] wgpu::RequestAdapterError),
+
+    #[error("failed to create GPU device: {0}")]
+    Device(#[from] wgpu::RequestDeviceError),
+}
+
+/// Any adapter, software ones (llvmpipe, lavapipe) included
+fn request_adapter() -> Result<wgpu::Adapter, Error> {
+    let instance = wgpu::Instance::default();
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+        .or_else(|err| {
+            log::info!("no hardware GPU adapter ({err}), trying fallback adapter");
+            block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                force_fallback_adapter: true,
+                ..Default::default()
+            }))
+        })?;
+
+    Ok(adapter)
+}
+
+/// Whether `request_device` can find an adapter, probed once per process
+pub fn available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+    *AVAILABLE.get_or_init(|| request_adapter().map_err(|err| log::info!("{err}")).is_ok())
+}
+
+/// Request a device on any adapter, software ones (llvmpipe, lavapipe) included
+pub fn request_device(label: &str) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue), Error> {
+    let adapter = request_adapter()?;
+
+    let (device, queue) = block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+        label: Some(label),
+        required_limits: adapter.limits(),
+        ..Default::default()
+    }))?;
+
+    Ok((adapter, device, queue))
+}
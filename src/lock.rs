@@ -1,12 +1,26 @@
+use std::{cell::RefCell, rc::Rc};
+
 use gtk::{Application, gdk, glib, prelude::*};
 
-use crate::{config, log, widgets::window::LockWindow};
+use crate::{
+    config,
+    log,
+    screenshot::{Screenshot, Screenshots},
+    state::LockState,
+    widgets::window::LockWindow,
+};
 
 #[derive(Clone, glib::Downgrade, Debug, Default)]
-pub struct Lock(pub gtk_session_lock::Instance);
+pub struct Lock(
+    pub gtk_session_lock::Instance,
+    Rc<RefCell<Screenshots>>,
+    LockState,
+);
 
 impl Lock {
-    fn locked(app: &Application, parent: Option<i32>) {
+    fn locked(app: &Application, parent: Option<i32>, state: &LockState) {
+        state.set_locked_at(glib::DateTime::now_local().ok());
+
         glib::unix_signal_add_local_once(
             libc::SIGUSR1,
             glib::clone!(
@@ -38,6 +52,8 @@ impl Lock {
         monitor: &gdk::Monitor,
         app: &Application,
         config: &config::Config,
+        screenshots: &RefCell<Screenshots>,
+        state: &LockState,
     ) {
         // This function will be called once for each monitor (aka output)
         // present when the session becomes locked, and also
@@ -45,12 +61,10 @@ impl Lock {
         let w = LockWindow::builder()
             .application(app)
             .lock(lock)
-            .start_hidden(config.get_start_hidden())
-            .idle_timeout(config.get_idle_timeout())
-            .time_format(config.get_time_format())
-            .date_format(config.get_date_format())
-            .background(config.get_background())
+            .config(config)
             .monitor(monitor)
+            .state(state)
+            .screenshot(Self::take_screenshot_from(screenshots, monitor))
             .build();
 
         lock.assign_window_to_monitor(&w, monitor);
@@ -59,11 +73,15 @@ impl Lock {
 
     pub fn new(app: &gtk::Application, parent: Option<i32>, config: &config::Config) -> Self {
         let lock = gtk_session_lock::Instance::new();
+        let screenshots = Rc::new(RefCell::new(Screenshots::new()));
+        let state = LockState::default();
 
         lock.connect_locked(glib::clone!(
             #[weak]
             app,
-            move |_| Self::locked(&app, parent)
+            #[weak]
+            state,
+            move |_| Self::locked(&app, parent, &state)
         ));
         lock.connect_failed(glib::clone!(
             #[weak]
@@ -80,10 +98,40 @@ impl Lock {
             app,
             #[strong]
             config,
-            move |lock, monitor| Self::on_monitor_present(lock, monitor, &app, &config)
+            #[strong]
+            screenshots,
+            #[strong]
+            state,
+            move |lock, monitor| {
+                Self::on_monitor_present(lock, monitor, &app, &config, &screenshots, &state)
+            }
         ));
 
-        Self(lock)
+        Self(lock, screenshots, state)
+    }
+
+    pub fn state(&self) -> &LockState {
+        &self.2
+    }
+
+    /// Screenshots taken before locking, windows pick them up by monitor
+    pub fn set_screenshots(&self, screenshots: Screenshots) {
+        *self.1.borrow_mut() = screenshots;
+    }
+
+    pub fn take_screenshot(&self, monitor: &gdk::Monitor) -> Option<Screenshot> {
+        Self::take_screenshot_from(&self.1, monitor)
+    }
+
+    fn take_screenshot_from(
+        screenshots: &RefCell<Screenshots>,
+        monitor: &gdk::Monitor,
+    ) -> Option<Screenshot> {
+        // Monitor plugged in after locking has no screenshot,
+        // capturing it now would capture the lock screen itself.
+        screenshots
+            .borrow_mut()
+            .remove(monitor.connector()?.as_str())
     }
 
     pub fn enlock(&self) -> bool {
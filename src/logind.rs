@@ -0,0 +1,182 @@
+use gtk::{gio, glib, prelude::*};
+
+pub const DESTINATION: &str = "org.freedesktop.login1";
+pub const PATH: &str = "/org/freedesktop/login1";
+pub const MANAGER: &str = "org.freedesktop.login1.Manager";
+
+/// Call `method` of the manager on the system bus
+pub async fn call(
+    method: &str,
+    parameters: Option<glib::Variant>,
+) -> Result<glib::Variant, glib::Error> {
+    let connection = gio::bus_get_future(gio::BusType::System).await?;
+
+    connection
+        .call_future(
+            Some(DESTINATION),
+            PATH,
+            MANAGER,
+            method,
+            parameters.as_ref(),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await
+}
+
+/// Id of the session waylock runs in, `auto` lets logind pick the caller's one
+pub fn session_id() -> String {
+    std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".into())
+}
+
+/// Answer of `Can*` methods: `yes`, `no`, `challenge` or `na`
+pub async fn can(method: &str) -> Result<String, glib::Error> {
+    let reply = call(method, None).await?;
+
+    Ok(reply.child_value(0).get::<String>().unwrap_or_default())
+}
+
+/// `who` and `why` of inhibitors blocking `what`, e.g. `shutdown` or `sleep`
+pub async fn blocking_inhibitors(what: &str) -> Result<Vec<(String, String)>, glib::Error> {
+    let reply = call("ListInhibitors", None).await?;
+
+    let (inhibitors,) = reply
+        .get::<(Vec<(String, String, String, String, u32, u32)>,)>()
+        .unwrap_or_default();
+
+    Ok(inhibitors
+        .into_iter()
+        .filter(|(whats, _, _, mode, _, _)| mode == "block" && whats.split(':').any(|w| w == what))
+        .map(|(_, who, why, _, _, _)| (who, why))
+        .collect())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::test_bus::{self, Service};
+
+    /// Calls other than `Can*`, with their parameters
+    pub type Calls = Rc<RefCell<Vec<(String, glib::Variant)>>>;
+
+    const ACTIONS: [&str; 5] = ["PowerOff", "Reboot", "Suspend", "Hibernate", "HybridSleep"];
+
+    fn introspection() -> String {
+        let actions = ACTIONS
+            .iter()
+            .map(|action| {
+                format!(
+                    r#"<method name="Can{action}"><arg type="s" direction="out"/></method>
+                    <method name="{action}"><arg type="b" direction="in"/></method>"#
+                )
+            })
+            .collect::<String>();
+
+        format!(
+            r#"<node><interface name="{MANAGER}">{actions}
+            <method name="TerminateSession"><arg type="s" direction="in"/></method>
+            <method name="ListInhibitors"><arg type="a(ssssuu)" direction="out"/></method>
+            </interface></node>"#
+        )
+    }
+
+    /// logind answering `Can*` methods from `answers`, others fail
+    pub fn manager(answers: &[(&'static str, &'static str)], calls: &Calls) -> Service {
+        let answers = answers.to_vec();
+        let calls = calls.clone();
+
+        Service::new(
+            DESTINATION,
+            PATH,
+            MANAGER,
+            &introspection(),
+            move |method, parameters| {
+                if method.starts_with("Can") {
+                    return answers
+                        .iter()
+                        .find(|(name, _)| *name == method)
+                        .map(|(_, answer)| (*answer,).to_variant())
+                        .ok_or_else(|| {
+                            glib::Error::new(gio::IOErrorEnum::NotSupported, "not stubbed")
+                        });
+                }
+
+                if method == "ListInhibitors" {
+                    let inhibitors = vec![
+                        (
+                            "shutdown:sleep",
+                            "Updater",
+                            "Installing",
+                            "block",
+                            0u32,
+                            1u32,
+                        ),
+                        ("sleep", "Player", "Playing", "delay", 1000, 2),
+                        ("idle", "Browser", "Video", "block", 1000, 3),
+                    ];
+                    return Ok((inhibitors,).to_variant());
+                }
+
+                calls.borrow_mut().push((method.into(), parameters));
+                Ok(().to_variant())
+            },
+            |_| unreachable!("manager stub has no properties"),
+        )
+    }
+
+    #[test]
+    fn can_and_call() {
+        test_bus::run(async {
+            let calls = Calls::default();
+            let _manager = manager(
+                &[("CanPowerOff", "yes"), ("CanReboot", "challenge")],
+                &calls,
+            );
+
+            assert_eq!(can("CanPowerOff").await.unwrap(), "yes");
+            assert_eq!(can("CanReboot").await.unwrap(), "challenge");
+            assert!(can("CanSuspend").await.is_err());
+
+            call("PowerOff", Some((false,).to_variant())).await.unwrap();
+            call("TerminateSession", Some(("c2",).to_variant()))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                *calls.borrow(),
+                [
+                    (String::from("PowerOff"), (false,).to_variant()),
+                    (String::from("TerminateSession"), ("c2",).to_variant()),
+                ]
+            );
+
+            // Parameters are checked against introspection
+            assert!(call("Reboot", None).await.is_err());
+        });
+    }
+
+    #[test]
+    fn inhibitors() {
+        test_bus::run(async {
+            let _manager = manager(&[], &Calls::default());
+
+            assert_eq!(
+                blocking_inhibitors("shutdown").await.unwrap(),
+                [(String::from("Updater"), String::from("Installing"))]
+            );
+            assert_eq!(
+                blocking_inhibitors("sleep").await.unwrap(),
+                [(String::from("Updater"), String::from("Installing"))]
+            );
+            assert!(
+                blocking_inhibitors("handle-lid-switch")
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+        });
+    }
+}
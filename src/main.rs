@@ -27,16 +27,34 @@
 // #![warn(clippy::cargo)]
 
 use clap::Parser as _;
-use gtk::{Application, Settings, gio, glib, prelude::*};
+use gtk::{Application, Settings, gdk, gio, glib, prelude::*};
 
 mod args;
+mod background;
 mod blur;
+mod cache;
+mod calendar;
 mod config;
 mod css;
+mod display_manager;
+mod effects;
+#[cfg(feature = "gpu")]
+mod gpu;
 mod lock;
 mod log;
+mod logind;
 mod messages;
+mod network_manager;
 mod pam;
+mod screenshot;
+#[cfg(feature = "gpu")]
+mod shader;
+mod state;
+#[cfg(test)]
+mod test_bus;
+mod theme;
+mod upower;
+mod video;
 mod widgets;
 
 #[cfg(feature = "idlenotifier")]
@@ -139,12 +157,33 @@ fn main() -> glib::ExitCode {
         args.config = config::load_config(path).merge(args.config);
     }
 
+    // Before anything may spawn a thread
+    video::configure_decoder(args.config.get_video_decoder());
+
+    #[cfg(feature = "gpu")]
+    if let Some(path) = args.config.get_shader()
+        && let Err(err) = shader::check(path)
+    {
+        log::warning!("shader {path:?} disabled: {err}");
+        args.config.clear_shader();
+    }
+
     // TODO
     // For many reasons we shall initialize gtk manually and earlier.
     gtk::init().unwrap();
 
+    if let Some(args::Command::Cache { action }) = args.command {
+        return run_cache(action, &args.config);
+    }
+
+    let variant = theme::variant(&args.config);
+    if variant.is_dark() {
+        args.config.use_dark_background();
+    }
+
     if let Some(settings) = Settings::default() {
         settings.set_gtk_theme_name(args.config.get_gtk_theme().map(String::as_str));
+        settings.set_gtk_application_prefer_dark_theme(variant.is_dark());
     }
 
     println!("{:?}", args.config);
@@ -155,7 +194,7 @@ fn main() -> glib::ExitCode {
     app.connect_activate(glib::clone!(
         #[weak]
         lock,
-        move |app| activate(app, &lock, &args)
+        move |app| activate(app, &lock, &args, variant)
     ));
 
     app.connect_shutdown(glib::clone!(
@@ -176,10 +215,28 @@ fn main() -> glib::ExitCode {
     app.run_with_args::<glib::GString>(&[])
 }
 
-fn activate(app: &Application, lock: &Lock, args: &args::Args) {
+fn run_cache(action: args::CacheAction, config: &config::Config) -> glib::ExitCode {
+    let result = match action {
+        args::CacheAction::Warm => cache::warm(config),
+        args::CacheAction::Clear => {
+            cache::Cache::new(config.get_cache_size()).and_then(|cache| Ok(cache.clear()?))
+        }
+    };
+
+    match result {
+        Ok(()) => glib::ExitCode::SUCCESS,
+        Err(err) => {
+            log::error!("{err}");
+            glib::ExitCode::FAILURE
+        }
+    }
+}
+
+fn activate(app: &Application, lock: &Lock, args: &args::Args, variant: theme::Variant) {
     // let _hold_guard = app.hold(); // TODO
 
-    if !Lock::is_supported() {
+    // Preview shows a regular window, no session lock is needed
+    if !args.preview && !Lock::is_supported() {
         log::fatal!("your compositor does not support ext-session-lock");
     }
 
@@ -206,6 +263,18 @@ window:not(.hidden) .time {{
 window:not(.hidden) .date {{
     font-size: 20pt;
 }}
+.zone-name {{
+    font-size: 10pt;
+    opacity: 0.7;
+}}
+.zone-time {{
+    font-size: 18pt;
+    font-family: monospace;
+}}
+.zone-date {{
+    font-size: 10pt;
+    font-family: monospace;
+}}
 
 .error-label {{
     color: red;
@@ -222,26 +291,100 @@ window:not(.hidden) .date {{
     //         );
 
     if let Some(style_path) = args.config.get_style() {
+        let variant_path = variant.style(&style_path);
+
         css::attach_custom_style(style_path);
+
+        if variant_path.exists() {
+            css::attach_custom_style(variant_path);
+        }
     }
 
-    if true {
-        lock.enlock();
+    if args.config.get_backgrounds().has_screenshot() {
+        // Capture before locking, afterwards outputs show the lock screen itself
+        let outputs = screenshot::connectors();
+        let backends = args.config.get_screenshot_backends().to_vec();
+        let command = args.config.get_screenshot_command().to_owned();
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak]
+            app,
+            #[weak]
+            lock,
+            #[strong]
+            args,
+            async move {
+                // A stuck backend must not keep the session unlocked, screenshot backgrounds
+                // fall back to the next one when there is nothing captured
+                let deadline = std::time::Instant::now() + screenshot::TIMEOUT;
+                let capture = gio::spawn_blocking(move || {
+                    screenshot::capture_all(&outputs, &backends, &command, deadline)
+                });
+
+                match glib::future_with_timeout(screenshot::TIMEOUT, capture).await {
+                    Ok(Ok(screenshots)) => lock.set_screenshots(screenshots),
+                    Ok(Err(_)) => log::warning!("screenshot capture failed"),
+                    Err(_) => log::warning!("screenshot capture timed out, locking without"),
+                }
+                start(&app, &lock, &args);
+            }
+        ));
+    } else {
+        start(app, lock, args);
+    }
+}
+
+fn start(app: &Application, lock: &Lock, args: &args::Args) {
+    if args.preview {
+        preview(app, lock, args);
     } else {
-        let w = window::LockWindow::builder()
-            .application(app)
-            .lock(&lock.0)
-            .start_hidden(args.config.get_start_hidden())
-            .idle_timeout(args.config.get_idle_timeout())
-            .time_format(args.config.get_time_format())
-            .date_format(args.config.get_date_format())
-            .background(args.config.get_background())
-            .build();
-
-        w.present();
+        lock.enlock();
     }
 }
 
+/// Show lock window as a regular toplevel, the session is never locked
+fn preview(app: &Application, lock: &Lock, args: &args::Args) {
+    let monitor = gdk::Display::default()
+        .and_then(|display| display.monitors().item(args.monitor))
+        .and_then(|monitor| monitor.downcast::<gdk::Monitor>().ok());
+
+    if monitor.is_none() {
+        log::warning!("monitor {} not found", args.monitor);
+    }
+
+    // Preview counts as locked from now
+    lock.state().set_locked_at(glib::DateTime::now_local().ok());
+
+    let mut builder = window::LockWindow::builder()
+        .application(app)
+        .lock(&lock.0)
+        .state(lock.state())
+        .config(&args.config)
+        .preview_password(&args.preview_password);
+
+    if let Some(ref monitor) = monitor {
+        builder = builder
+            .monitor(monitor)
+            .screenshot(lock.take_screenshot(monitor));
+    }
+
+    let w = builder.build();
+
+    if let Some(ref monitor) = monitor {
+        let geometry = monitor.geometry();
+        w.set_default_size(geometry.width(), geometry.height());
+    }
+
+    // Nothing holds us when the window is gone
+    app.connect_window_removed(|app, _| {
+        if app.windows().is_empty() {
+            app.quit();
+        }
+    });
+
+    w.present();
+}
+
 fn shutdown(lock: &lock::Lock) {
     //lock.unlock();
 }
@@ -0,0 +1,446 @@
+use std::rc::Rc;
+
+use gtk::{gio, glib, prelude::*};
+
+pub const DESTINATION: &str = "org.freedesktop.NetworkManager";
+pub const PATH: &str = "/org/freedesktop/NetworkManager";
+pub const MANAGER: &str = "org.freedesktop.NetworkManager";
+const ACTIVE_CONNECTION: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const ACCESS_POINT: &str = "org.freedesktop.NetworkManager.AccessPoint";
+pub const PROPERTIES: &str = "org.freedesktop.DBus.Properties";
+
+/// `NM_STATE_*`, anything short of reaching the site network is offline,
+/// as a local only connection has no route beyond this machine
+const STATE_ASLEEP: u32 = 10;
+const STATE_CONNECTING: u32 = 40;
+const STATE_CONNECTED_LOCAL: u32 = 50;
+const STATE_CONNECTED_SITE: u32 = 60;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Link {
+    /// NetworkManager is not running, nothing is known
+    #[default]
+    Unknown,
+    Offline,
+    Connecting,
+    Wired {
+        name: String,
+    },
+    Wireless {
+        ssid: String,
+        strength: u8,
+    },
+    Other {
+        name: String,
+    },
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Status {
+    pub link: Link,
+    /// Name of an active VPN connection
+    pub vpn: Option<String>,
+    /// Object paths whose changes affect the status
+    pub watched: Vec<String>,
+}
+
+impl Status {
+    pub fn is_offline(&self) -> bool {
+        matches!(self.link, Link::Offline | Link::Connecting)
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self.link {
+            Link::Unknown | Link::Offline => "network-offline-symbolic",
+            Link::Connecting => "network-idle-symbolic",
+            Link::Wired { .. } | Link::Other { .. } => "network-wired-symbolic",
+            Link::Wireless { strength, .. } => match strength {
+                81.. => "network-wireless-signal-excellent-symbolic",
+                56..=80 => "network-wireless-signal-good-symbolic",
+                31..=55 => "network-wireless-signal-ok-symbolic",
+                6..=30 => "network-wireless-signal-weak-symbolic",
+                _ => "network-wireless-signal-none-symbolic",
+            },
+        }
+    }
+
+    /// "Home (72%), VPN Work"
+    pub fn describe(&self) -> String {
+        let link = match &self.link {
+            Link::Unknown => String::new(),
+            Link::Offline => "Offline".into(),
+            Link::Connecting => "Connecting".into(),
+            Link::Wired { name } | Link::Other { name } => name.clone(),
+            Link::Wireless { ssid, strength } => format!("{ssid} ({strength}%)"),
+        };
+
+        match &self.vpn {
+            Some(vpn) => format!("{link}, VPN {vpn}"),
+            None => link,
+        }
+    }
+}
+
+/// Calls back when NetworkManager starts or stops, and once right away
+pub struct Watch {
+    /// Type of the watcher id is not exported by gio, so it is kept in the closure
+    unwatch: Option<Box<dyn FnOnce()>>,
+}
+
+impl Watch {
+    pub fn new(connection: &gio::DBusConnection, callback: impl Fn() + 'static) -> Self {
+        let callback = Rc::new(callback);
+        let appeared = callback.clone();
+
+        let id = gio::bus_watch_name_on_connection(
+            connection,
+            DESTINATION,
+            gio::BusNameWatcherFlags::NONE,
+            move |_, _, _| appeared(),
+            move |_, _| callback(),
+        );
+
+        Self {
+            unwatch: Some(Box::new(move || gio::bus_unwatch_name(id))),
+        }
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        if let Some(unwatch) = self.unwatch.take() {
+            unwatch();
+        }
+    }
+}
+
+impl std::fmt::Debug for Watch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watch").finish_non_exhaustive()
+    }
+}
+
+async fn property(
+    connection: &gio::DBusConnection,
+    path: &str,
+    interface: &str,
+    name: &str,
+) -> Option<glib::Variant> {
+    let reply = connection
+        .call_future(
+            Some(DESTINATION),
+            path,
+            PROPERTIES,
+            "Get",
+            Some(&(interface, name).to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await
+        .ok()?;
+
+    reply.child_value(0).as_variant()
+}
+
+/// Current connectivity, `Link::Unknown` if NetworkManager does not answer
+pub async fn status(connection: &gio::DBusConnection) -> Status {
+    let Some(state) = property(connection, PATH, MANAGER, "State")
+        .await
+        .and_then(|state| state.get::<u32>())
+    else {
+        return Status::default();
+    };
+
+    let link = match state {
+        STATE_ASLEEP..STATE_CONNECTING | STATE_CONNECTED_LOCAL => Link::Offline,
+        STATE_CONNECTING => Link::Connecting,
+        STATE_CONNECTED_SITE.. => Link::Unknown,
+        _ => return Status::default(),
+    };
+
+    let mut status = Status {
+        link,
+        vpn: None,
+        watched: vec![PATH.into()],
+    };
+
+    if status.link != Link::Unknown {
+        return status;
+    }
+
+    let primary = property(connection, PATH, MANAGER, "PrimaryConnection")
+        .await
+        .and_then(|path| path.str().map(String::from));
+    let mut active = property(connection, PATH, MANAGER, "ActiveConnections")
+        .await
+        .and_then(|paths| paths.get::<Vec<glib::variant::ObjectPath>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|path| path.as_str().to_owned())
+        .collect::<Vec<_>>();
+
+    // Primary connection first, it carries the default route
+    if let Some(primary) = primary {
+        active.sort_by_key(|path| *path != primary);
+    }
+
+    for path in active {
+        let kind = property(connection, &path, ACTIVE_CONNECTION, "Type")
+            .await
+            .and_then(|kind| kind.get::<String>())
+            .unwrap_or_default();
+        let name = property(connection, &path, ACTIVE_CONNECTION, "Id")
+            .await
+            .and_then(|name| name.get::<String>())
+            .unwrap_or_default();
+
+        status.watched.push(path.clone());
+
+        match &*kind {
+            "vpn" | "wireguard" => {
+                status.vpn.get_or_insert(name);
+            }
+            "loopback" => {}
+            _ if status.link != Link::Unknown => {}
+            "802-3-ethernet" => status.link = Link::Wired { name },
+            "802-11-wireless" => {
+                let access_point = property(connection, &path, ACTIVE_CONNECTION, "SpecificObject")
+                    .await
+                    .and_then(|path| path.str().map(String::from))
+                    .filter(|path| path != "/");
+
+                let (ssid, strength) = match &access_point {
+                    Some(access_point) => (
+                        property(connection, access_point, ACCESS_POINT, "Ssid")
+                            .await
+                            .and_then(|ssid| ssid.get::<Vec<u8>>())
+                            .map(|ssid| String::from_utf8_lossy(&ssid).into_owned())
+                            .unwrap_or(name),
+                        property(connection, access_point, ACCESS_POINT, "Strength")
+                            .await
+                            .and_then(|strength| strength.get::<u8>())
+                            .unwrap_or_default(),
+                    ),
+                    None => (name, 0),
+                };
+
+                status.watched.extend(access_point);
+                status.link = Link::Wireless { ssid, strength };
+            }
+            _ => status.link = Link::Other { name },
+        }
+    }
+
+    if status.link == Link::Unknown {
+        // Connected, but only through connections not shown, e.g. loopback
+        status.link = Link::Other {
+            name: "Connected".into(),
+        };
+    }
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::test_bus::{self, Service};
+
+    const INTROSPECTION: &str = r#"<node><interface name="org.freedesktop.NetworkManager">
+        <property name="State" type="u" access="read"/>
+        <property name="PrimaryConnection" type="o" access="read"/>
+        <property name="ActiveConnections" type="ao" access="read"/>
+    </interface></node>"#;
+
+    const ACTIVE_INTROSPECTION: &str = r#"<node>
+        <interface name="org.freedesktop.NetworkManager.Connection.Active">
+            <property name="Type" type="s" access="read"/>
+            <property name="Id" type="s" access="read"/>
+            <property name="SpecificObject" type="o" access="read"/>
+        </interface>
+    </node>"#;
+
+    const ACCESS_POINT_INTROSPECTION: &str = r#"<node>
+        <interface name="org.freedesktop.NetworkManager.AccessPoint">
+            <property name="Ssid" type="ay" access="read"/>
+            <property name="Strength" type="y" access="read"/>
+        </interface>
+    </node>"#;
+
+    const WIRELESS: &str = "/org/freedesktop/NetworkManager/ActiveConnection/1";
+    const WIRED: &str = "/org/freedesktop/NetworkManager/ActiveConnection/2";
+    const VPN: &str = "/org/freedesktop/NetworkManager/ActiveConnection/3";
+    const HOTSPOT: &str = "/org/freedesktop/NetworkManager/ActiveConnection/4";
+    const HOME: &str = "/org/freedesktop/NetworkManager/AccessPoint/1";
+
+    fn object_path(path: &str) -> glib::Variant {
+        glib::variant::ObjectPath::try_from(path)
+            .unwrap()
+            .to_variant()
+    }
+
+    fn manager_stub(state: &Rc<Cell<u32>>) -> Service {
+        connected_stub(state, "/", &[])
+    }
+
+    /// Manager with `active` connections, `primary` of them carrying the default route
+    fn connected_stub(
+        state: &Rc<Cell<u32>>,
+        primary: &'static str,
+        active: &'static [&'static str],
+    ) -> Service {
+        let state = state.clone();
+
+        Service::new(
+            DESTINATION,
+            PATH,
+            MANAGER,
+            INTROSPECTION,
+            |_, _| unreachable!("manager stub has no methods"),
+            move |name| match name {
+                "State" => state.get().to_variant(),
+                "PrimaryConnection" => object_path(primary),
+                _ => active
+                    .iter()
+                    .map(|path| glib::variant::ObjectPath::try_from(*path).unwrap())
+                    .collect::<Vec<_>>()
+                    .to_variant(),
+            },
+        )
+    }
+
+    /// Export all the connections, the wireless one is on access point `HOME`,
+    /// the hotspot on none known
+    fn export_connections(manager: &mut Service) {
+        for (path, kind, id, specific) in [
+            (WIRELESS, "802-11-wireless", "Home connection", HOME),
+            (WIRED, "802-3-ethernet", "Wired connection 1", "/"),
+            (VPN, "vpn", "Work", "/"),
+            (HOTSPOT, "802-11-wireless", "Hotspot", "/"),
+        ] {
+            manager.export(
+                path,
+                ACTIVE_CONNECTION,
+                ACTIVE_INTROSPECTION,
+                move |name| match name {
+                    "Type" => kind.to_variant(),
+                    "Id" => id.to_variant(),
+                    _ => object_path(specific),
+                },
+            );
+        }
+
+        manager.export(
+            HOME,
+            ACCESS_POINT,
+            ACCESS_POINT_INTROSPECTION,
+            |name| match name {
+                "Ssid" => b"Home".to_vec().to_variant(),
+                _ => 72u8.to_variant(),
+            },
+        );
+    }
+
+    #[test]
+    fn states() {
+        test_bus::run(async {
+            let connection = gio::bus_get_future(gio::BusType::System).await.unwrap();
+
+            // Not running
+            assert_eq!(status(&connection).await, Status::default());
+
+            let state = Rc::new(Cell::new(0));
+            let _manager = manager_stub(&state);
+
+            for (nm_state, link, offline) in [
+                (20, Link::Offline, true),
+                (40, Link::Connecting, true),
+                (50, Link::Offline, true),
+                (
+                    60,
+                    Link::Other {
+                        name: "Connected".into(),
+                    },
+                    false,
+                ),
+                (
+                    70,
+                    Link::Other {
+                        name: "Connected".into(),
+                    },
+                    false,
+                ),
+            ] {
+                state.set(nm_state);
+                let status = status(&connection).await;
+
+                assert_eq!(status.link, link, "state {nm_state}");
+                assert_eq!(status.is_offline(), offline, "state {nm_state}");
+                assert_eq!(status.watched, [PATH]);
+            }
+
+            // Unknown is neither online nor offline
+            state.set(0);
+            assert_eq!(status(&connection).await, Status::default());
+            assert!(!Status::default().is_offline());
+        });
+    }
+
+    #[test]
+    fn connections() {
+        test_bus::run(async {
+            let connection = gio::bus_get_future(gio::BusType::System).await.unwrap();
+            let state = Rc::new(Cell::new(70));
+
+            // Primary connection wins over the ones listed before it
+            let mut manager = connected_stub(&state, WIRELESS, &[WIRED, WIRELESS]);
+            export_connections(&mut manager);
+
+            let status = status(&connection).await;
+            assert_eq!(
+                status.link,
+                Link::Wireless {
+                    ssid: "Home".into(),
+                    strength: 72,
+                }
+            );
+            assert_eq!(status.vpn, None);
+            assert_eq!(status.watched, [PATH, WIRELESS, HOME, WIRED]);
+            assert_eq!(status.describe(), "Home (72%)");
+            drop(manager);
+
+            let mut manager = connected_stub(&state, WIRED, &[WIRELESS, WIRED]);
+            export_connections(&mut manager);
+
+            let status = status(&connection).await;
+            assert_eq!(
+                status.link,
+                Link::Wired {
+                    name: "Wired connection 1".into(),
+                }
+            );
+            // Access point of a connection not shown is not watched
+            assert_eq!(status.watched, [PATH, WIRED, WIRELESS]);
+            drop(manager);
+
+            // Access point is not known, name of the connection stands in for the SSID
+            let mut manager = connected_stub(&state, HOTSPOT, &[VPN, HOTSPOT]);
+            export_connections(&mut manager);
+
+            let status = status(&connection).await;
+            assert_eq!(
+                status.link,
+                Link::Wireless {
+                    ssid: "Hotspot".into(),
+                    strength: 0,
+                }
+            );
+            assert_eq!(status.vpn.as_deref(), Some("Work"));
+            assert_eq!(status.watched, [PATH, HOTSPOT, VPN]);
+            assert_eq!(status.describe(), "Hotspot (0%), VPN Work");
+        });
+    }
+}
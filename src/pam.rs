@@ -3,7 +3,14 @@ use std::{
     ffi::{OsStr, OsString},
 };
 
-use nonstick::{AuthnFlags, ConversationAdapter, Result, Transaction, TransactionBuilder};
+use nonstick::{
+    AuthnFlags,
+    ConversationAdapter,
+    ErrorCode,
+    Result,
+    Transaction,
+    TransactionBuilder,
+};
 
 struct StaticConversation<'a> {
     info: RefCell<&'a mut dyn FnMut(&OsStr)>,
@@ -57,3 +64,12 @@ pub fn authenticate(
         .authenticate(AuthnFlags::empty())
         .and_then(|_| transaction.account_management(AuthnFlags::empty()))
 }
+
+/// Compares a password against a fixed one, used in place of PAM in preview mode.
+pub fn authenticate_static(expected: &str, password: &str) -> Result<()> {
+    if expected == password {
+        Ok(())
+    } else {
+        Err(ErrorCode::AuthenticationError)
+    }
+}
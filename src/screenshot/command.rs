@@ -0,0 +1,86 @@
+use std::{
+    io::Read,
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use gtk::{gdk, glib};
+
+use super::{Captures, Error};
+use crate::log;
+
+/// Run `command` once per output with `{output}` replaced by connector name,
+/// image in any format gdk can load is expected on stdout.
+/// Commands still running at `deadline` are killed.
+pub fn capture(wanted: &[String], command: &str, deadline: Instant) -> Result<Captures, Error> {
+    let argv = glib::shell_parse_argv(command).map_err(|err| Error::Backend(err.to_string()))?;
+    let Some((program, args)) = argv.split_first() else {
+        return Err(Error::Backend("screenshot command is empty".into()));
+    };
+
+    Ok(wanted
+        .iter()
+        .filter_map(|output| {
+            let mut child = Command::new(program)
+                .args(
+                    args.iter()
+                        .map(|arg| arg.to_string_lossy().replace("{output}", output)),
+                )
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::inherit())
+                .spawn()
+                .map_err(|err| log::warning!("failed to run {program:?}: {err}"))
+                .ok()?;
+
+            // Drain stdout aside, a full pipe would block the command forever
+            let mut stdout = child.stdout.take()?;
+            let reader = thread::spawn(move || {
+                let mut bytes = Vec::new();
+                stdout.read_to_end(&mut bytes).map(|_| bytes)
+            });
+
+            let status = wait(&mut child, deadline)
+                .map_err(|err| log::warning!("{program:?} failed to capture {output}: {err}"))
+                .ok()?;
+
+            if !status.success() {
+                log::warning!("{program:?} failed to capture {output}: {status}");
+                return None;
+            }
+
+            let bytes = reader
+                .join()
+                .ok()?
+                .map_err(|err| log::warning!("failed to read screenshot of {output}: {err}"))
+                .ok()?;
+
+            let texture = gdk::Texture::from_bytes(&glib::Bytes::from_owned(bytes))
+                .map_err(|err| log::warning!("failed to load screenshot of {output}: {err}"))
+                .ok()?;
+
+            Some((output.clone(), crate::blur::from_texture(&texture)))
+        })
+        .collect())
+}
+
+/// Wait for `child` to exit, killing it at `deadline`
+fn wait(child: &mut Child, deadline: Instant) -> std::io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "timed out, killed",
+            ));
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,389 @@
+use std::{
+    fs::File,
+    os::{
+        fd::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+        unix::fs::FileExt,
+    },
+    time::Instant,
+};
+
+use wayland_client::{
+    Connection,
+    Dispatch,
+    DispatchError,
+    EventQueue,
+    QueueHandle,
+    WEnum,
+    delegate_noop,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::{
+        wl_buffer::WlBuffer,
+        wl_output::{self, WlOutput},
+        wl_registry::{self, WlRegistry},
+        wl_shm::{self, WlShm},
+        wl_shm_pool::WlShmPool,
+    },
+};
+use wayland_protocols::ext::{
+    image_capture_source::v1::client::{
+        ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+        ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1,
+    },
+    image_copy_capture::v1::client::{
+        ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1},
+        ext_image_copy_capture_manager_v1::{self, ExtImageCopyCaptureManagerV1},
+        ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+    },
+};
+
+use super::{Captures, Error};
+use crate::log;
+
+/// Formats we can turn into RGBA, in order of preference
+const FORMATS: [wl_shm::Format; 4] = [
+    wl_shm::Format::Abgr8888,
+    wl_shm::Format::Xbgr8888,
+    wl_shm::Format::Argb8888,
+    wl_shm::Format::Xrgb8888,
+];
+
+#[derive(Default)]
+struct Output {
+    name: Option<String>,
+    size: Option<(u32, u32)>,
+    formats: Vec<wl_shm::Format>,
+    /// Session has sent all buffer constraints
+    done: bool,
+    /// Frame is captured, or the reason it is not
+    result: Option<Result<(), String>>,
+}
+
+#[derive(Default)]
+struct State {
+    outputs: Vec<Output>,
+}
+
+/// Frame being captured into shared memory
+struct Pending {
+    index: usize,
+    file: File,
+    width: u32,
+    height: u32,
+    format: wl_shm::Format,
+    _pool: WlShmPool,
+    _buffer: WlBuffer,
+    _frame: ExtImageCopyCaptureFrameV1,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlOutput, usize> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlOutput,
+        event: wl_output::Event,
+        index: &usize,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            state.outputs[*index].name = Some(name);
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, usize> for State {
+    fn event(
+        state: &mut Self,
+        _: &ExtImageCopyCaptureSessionV1,
+        event: ext_image_copy_capture_session_v1::Event,
+        index: &usize,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        use ext_image_copy_capture_session_v1::Event;
+
+        let output = &mut state.outputs[*index];
+
+        match event {
+            Event::BufferSize { width, height } => output.size = Some((width, height)),
+            Event::ShmFormat {
+                format: WEnum::Value(format),
+            } => output.formats.push(format),
+            Event::Done => output.done = true,
+            Event::Stopped => output.result = Some(Err("session stopped".into())),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, usize> for State {
+    fn event(
+        state: &mut Self,
+        _: &ExtImageCopyCaptureFrameV1,
+        event: ext_image_copy_capture_frame_v1::Event,
+        index: &usize,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        use ext_image_copy_capture_frame_v1::Event;
+
+        let output = &mut state.outputs[*index];
+
+        match event {
+            Event::Ready => output.result = Some(Ok(())),
+            Event::Failed { reason } => output.result = Some(Err(format!("{reason:?}"))),
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(State: ignore WlShm);
+delegate_noop!(State: ignore WlBuffer);
+delegate_noop!(State: WlShmPool);
+delegate_noop!(State: ExtOutputImageCaptureSourceManagerV1);
+delegate_noop!(State: ExtImageCaptureSourceV1);
+delegate_noop!(State: ExtImageCopyCaptureManagerV1);
+
+fn memfd(size: u64) -> std::io::Result<File> {
+    let fd = unsafe { libc::memfd_create(c"waylock-screenshot".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+    file.set_len(size)?;
+
+    Ok(file)
+}
+
+/// Convert shm pixels in place, formats are little endian so `Abgr8888` is RGBA in memory
+fn to_rgba(pixels: &mut [u8], format: wl_shm::Format) {
+    use wl_shm::Format;
+
+    for pixel in pixels.chunks_exact_mut(4) {
+        if matches!(format, Format::Argb8888 | Format::Xrgb8888) {
+            pixel.swap(0, 2);
+        }
+
+        if matches!(format, Format::Xbgr8888 | Format::Xrgb8888) {
+            pixel[3] = u8::MAX;
+        }
+    }
+}
+
+/// Dispatch events arriving before `deadline`, a compositor that never answers fails the capture
+fn dispatch(
+    conn: &Connection,
+    queue: &mut EventQueue<State>,
+    state: &mut State,
+    deadline: Instant,
+) -> Result<(), Error> {
+    if queue.dispatch_pending(state)? > 0 {
+        return Ok(());
+    }
+
+    conn.flush().map_err(DispatchError::from)?;
+
+    // Events were queued meanwhile
+    let Some(guard) = queue.prepare_read() else {
+        queue.dispatch_pending(state)?;
+        return Ok(());
+    };
+
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    let mut fd = libc::pollfd {
+        fd: guard.connection_fd().as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    match unsafe { libc::poll(&mut fd, 1, timeout.as_millis().min(i32::MAX as u128) as i32) } {
+        0 => return Err(Error::Timeout),
+        ..0 => {
+            let err = std::io::Error::last_os_error();
+            // Dropping the guard cancels the read, caller tries again
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                return Ok(());
+            }
+            return Err(err.into());
+        }
+        _ => {}
+    }
+
+    guard.read().map_err(DispatchError::from)?;
+    queue.dispatch_pending(state)?;
+
+    Ok(())
+}
+
+pub fn capture(wanted: &[String], deadline: Instant) -> Result<Captures, Error> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+    let qh = queue.handle();
+
+    let shm: WlShm = globals
+        .bind(&qh, 1..=1, ())
+        .map_err(|_| Error::Unsupported("wl_shm"))?;
+    let sources: ExtOutputImageCaptureSourceManagerV1 = globals
+        .bind(&qh, 1..=1, ())
+        .map_err(|_| Error::Unsupported("ext-image-capture-source-v1"))?;
+    let manager: ExtImageCopyCaptureManagerV1 = globals
+        .bind(&qh, 1..=1, ())
+        .map_err(|_| Error::Unsupported("ext-image-copy-capture-v1"))?;
+
+    let mut state = State::default();
+
+    // Connector names come with wl_output version 4
+    let outputs = globals
+        .contents()
+        .with_list(|list| {
+            list.iter()
+                .filter(|global| global.interface == "wl_output")
+                .map(|global| (global.name, global.version))
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, version))| {
+            state.outputs.push(Output::default());
+            globals
+                .registry()
+                .bind::<WlOutput, _, _>(name, version.min(4), &qh, index)
+        })
+        .collect::<Vec<_>>();
+
+    queue.roundtrip(&mut state)?;
+
+    let sessions = outputs
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            state.outputs[*index]
+                .name
+                .as_ref()
+                .is_some_and(|name| wanted.contains(name))
+        })
+        .map(|(index, output)| {
+            let source = sources.create_source(output, &qh, ());
+            let session = manager.create_session(
+                &source,
+                ext_image_copy_capture_manager_v1::Options::empty(),
+                &qh,
+                index,
+            );
+
+            (index, session)
+        })
+        .collect::<Vec<_>>();
+
+    while sessions.iter().any(|(index, _)| {
+        let output = &state.outputs[*index];
+        !output.done && output.result.is_none()
+    }) {
+        dispatch(&conn, &mut queue, &mut state, deadline)?;
+    }
+
+    let mut pending = Vec::new();
+
+    for (index, session) in &sessions {
+        let output = &mut state.outputs[*index];
+
+        if output.result.is_some() {
+            continue;
+        }
+
+        let Some((width, height)) = output.size else {
+            output.result = Some(Err("no buffer size".into()));
+            continue;
+        };
+
+        let Some(format) = FORMATS
+            .into_iter()
+            .find(|format| output.formats.contains(format))
+        else {
+            output.result = Some(Err(format!("unsupported formats {:?}", output.formats)));
+            continue;
+        };
+
+        let stride = width * 4;
+        let size = stride * height;
+        let file = memfd(u64::from(size))?;
+        let pool = shm.create_pool(file.as_fd(), size as i32, &qh, ());
+        let buffer = pool.create_buffer(
+            0,
+            width as i32,
+            height as i32,
+            stride as i32,
+            format,
+            &qh,
+            (),
+        );
+
+        let frame = session.create_frame(&qh, *index);
+        frame.attach_buffer(&buffer);
+        frame.damage_buffer(0, 0, width as i32, height as i32);
+        frame.capture();
+
+        pending.push(Pending {
+            index: *index,
+            file,
+            width,
+            height,
+            format,
+            _pool: pool,
+            _buffer: buffer,
+            _frame: frame,
+        });
+    }
+
+    while pending
+        .iter()
+        .any(|frame| state.outputs[frame.index].result.is_none())
+    {
+        dispatch(&conn, &mut queue, &mut state, deadline)?;
+    }
+
+    for (index, _) in &sessions {
+        let output = &state.outputs[*index];
+        if let (Some(name), Some(Err(reason))) = (&output.name, &output.result) {
+            log::warning!("failed to capture {name}: {reason}");
+        }
+    }
+
+    Ok(pending
+        .into_iter()
+        .filter_map(|frame| {
+            let output = &state.outputs[frame.index];
+            let name = output.name.clone()?;
+            output.result.as_ref()?.as_ref().ok()?;
+
+            let mut pixels = vec![0; (frame.width * frame.height * 4) as usize];
+            frame
+                .file
+                .read_exact_at(&mut pixels, 0)
+                .map_err(|err| log::warning!("failed to read screenshot of {name}: {err}"))
+                .ok()?;
+            to_rgba(&mut pixels, frame.format);
+
+            Some((
+                name,
+                blur::Image {
+                    width: frame.width,
+                    height: frame.height,
+                    pixels: bytemuck::cast_vec(pixels),
+                },
+            ))
+        })
+        .collect())
+}
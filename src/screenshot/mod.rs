@@ -0,0 +1,213 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use gtk::{gdk, glib, prelude::*};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "screenshot")]
+use crate::log;
+
+#[cfg(feature = "screenshot")]
+mod command;
+#[cfg(feature = "screenshot")]
+mod ext;
+#[cfg(feature = "screenshot")]
+mod wlr;
+#[cfg(feature = "screenshot")]
+mod xcap;
+
+/// Longest locking waits for screenshots
+pub const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest a single backend may take, so a stuck one leaves time for the next
+#[cfg(feature = "screenshot")]
+const BACKEND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Output contents captured before locking
+#[derive(Clone, glib::Boxed)]
+#[boxed_type(name = "WaylockScreenshot")]
+pub struct Screenshot {
+    pub image: Arc<blur::Image>,
+    /// Ready to show texture of `image`
+    pub texture: gdk::MemoryTexture,
+}
+
+impl std::fmt::Debug for Screenshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Screenshot")
+            .field("width", &self.image.width)
+            .field("height", &self.image.height)
+            .finish()
+    }
+}
+
+impl Screenshot {
+    pub fn new(image: blur::Image) -> Self {
+        Self {
+            texture: crate::blur::to_texture(&image),
+            image: Arc::new(image),
+        }
+    }
+
+    /// Copy of the image that effects may modify
+    pub fn to_image(&self) -> blur::Image {
+        blur::Image {
+            width: self.image.width,
+            height: self.image.height,
+            pixels: self.image.pixels.clone(),
+        }
+    }
+}
+
+/// Screenshots keyed by output connector name
+pub type Screenshots = HashMap<String, Screenshot>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// wlr-screencopy
+    Wlr,
+    /// ext-image-copy-capture
+    Ext,
+    Xcap,
+    /// External command, see `screenshot_command`
+    Command,
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Wlr => "wlr-screencopy",
+            Self::Ext => "ext-image-copy-capture",
+            Self::Xcap => "xcap",
+            Self::Command => "command",
+        })
+    }
+}
+
+#[cfg(feature = "screenshot")]
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to connect to wayland: {0}")]
+    Connect(#[from] wayland_client::ConnectError),
+
+    #[error("failed to get wayland globals: {0}")]
+    Globals(#[from] wayland_client::globals::GlobalError),
+
+    #[error("wayland error: {0}")]
+    Dispatch(#[from] wayland_client::DispatchError),
+
+    #[error("timed out")]
+    Timeout,
+
+    #[error("compositor does not support {0}")]
+    Unsupported(&'static str),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("{0}")]
+    Backend(String),
+}
+
+/// Captured images of the requested outputs
+#[cfg(feature = "screenshot")]
+type Captures = Vec<(String, blur::Image)>;
+
+#[cfg(feature = "screenshot")]
+impl Backend {
+    fn capture(
+        self,
+        outputs: &[String],
+        command: &str,
+        deadline: Instant,
+    ) -> Result<Captures, Error> {
+        match self {
+            Self::Wlr => wlr::capture(outputs),
+            Self::Ext => ext::capture(outputs, deadline),
+            Self::Xcap => xcap::capture(outputs),
+            Self::Command => command::capture(outputs, command, deadline),
+        }
+    }
+}
+
+/// Connector names of all monitors, e.g. `DP-1`
+pub fn connectors() -> Vec<String> {
+    gdk::Display::default()
+        .map(|display| display.monitors())
+        .map(|monitors| {
+            monitors
+                .iter::<gdk::Monitor>()
+                .flatten()
+                .filter_map(|monitor| monitor.connector())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Capture `outputs` trying `backends` in order until every output has a screenshot.
+/// It blocks so shall be called from a worker thread, no backend is tried after `deadline`
+/// and each one gets at most `BACKEND_TIMEOUT` of it.
+#[cfg(feature = "screenshot")]
+pub fn capture_all(
+    outputs: &[String],
+    backends: &[Backend],
+    command: &str,
+    deadline: Instant,
+) -> Screenshots {
+    let mut screenshots = Screenshots::new();
+
+    for &backend in backends {
+        let missing = outputs
+            .iter()
+            .filter(|output| !screenshots.contains_key(*output))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            break;
+        }
+
+        if Instant::now() >= deadline {
+            log::warning!("screenshot deadline passed before trying {backend}");
+            break;
+        }
+
+        let until = deadline.min(Instant::now() + BACKEND_TIMEOUT);
+
+        match backend.capture(&missing, command, until) {
+            Ok(captures) => {
+                for (output, image) in captures {
+                    if missing.contains(&output) {
+                        log::info!("captured {output} with {backend}");
+                        screenshots.insert(output, Screenshot::new(image));
+                    }
+                }
+            }
+            Err(err) => log::info!("{backend} capture failed: {err}"),
+        }
+    }
+
+    for output in outputs {
+        if !screenshots.contains_key(output) {
+            log::warning!("failed to capture {output}");
+        }
+    }
+
+    screenshots
+}
+
+#[cfg(not(feature = "screenshot"))]
+pub fn capture_all(
+    _outputs: &[String],
+    _backends: &[Backend],
+    _command: &str,
+    _deadline: Instant,
+) -> Screenshots {
+    Screenshots::new()
+}
@@ -0,0 +1,35 @@
+use grim_rs::Grim;
+
+use super::{Captures, Error};
+use crate::log;
+
+pub fn capture(wanted: &[String]) -> Result<Captures, Error> {
+    let mut grim = Grim::new().map_err(|err| Error::Backend(err.to_string()))?;
+    let outputs = grim
+        .get_outputs()
+        .map_err(|err| Error::Backend(err.to_string()))?;
+
+    Ok(outputs
+        .into_iter()
+        .filter(|output| wanted.iter().any(|name| name == output.name()))
+        .filter_map(|output| {
+            let name = output.name().to_string();
+            let result = grim
+                .capture_output(&name)
+                .map_err(|err| log::warning!("failed to capture {name}: {err}"))
+                .ok()?;
+            let width = result.width();
+            let height = result.height();
+            let data = result.into_data();
+
+            Some((
+                name,
+                blur::Image {
+                    width,
+                    height,
+                    pixels: bytemuck::cast_vec(data),
+                },
+            ))
+        })
+        .collect())
+}
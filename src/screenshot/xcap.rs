@@ -0,0 +1,26 @@
+use super::{Captures, Error};
+use crate::log;
+
+pub fn capture(wanted: &[String]) -> Result<Captures, Error> {
+    let monitors = ::xcap::Monitor::all().map_err(|err| Error::Backend(err.to_string()))?;
+
+    Ok(monitors
+        .into_iter()
+        .filter_map(|monitor| {
+            let name = monitor.name().ok().filter(|name| wanted.contains(name))?;
+            let image = monitor
+                .capture_image()
+                .map_err(|err| log::warning!("failed to capture {name}: {err}"))
+                .ok()?;
+
+            Some((
+                name,
+                blur::Image {
+                    width: image.width(),
+                    height: image.height(),
+                    pixels: bytemuck::cast_vec(image.into_raw()),
+                },
+            ))
+        })
+        .collect())
+}
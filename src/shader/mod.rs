@@ -0,0 +1,309 @@
+use std::path::Path;
+
+use futures::executor::block_on;
+use wgpu::util::DeviceExt as _;
+
+use crate::{gpu, log};
+
+const PRELUDE: &str = include_str!("prelude.wgsl");
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to read shader: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Gpu(#[from] gpu::Error),
+
+    #[error("failed to compile shader: {0}")]
+    Compile(wgpu::Error),
+}
+
+/// User fragment shader drawing the background
+pub struct Shader {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    uniforms: wgpu::Buffer,
+    target: wgpu::Texture,
+    readback: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    /// Readback rows are padded to `COPY_BYTES_PER_ROW_ALIGNMENT`
+    padded_row: u32,
+    monitor: u32,
+}
+
+impl Shader {
+    /// Shader renders `width`x`height` frames, `input` is available to it as `background`
+    pub fn new(
+        source: &str,
+        input: Option<&blur::Image>,
+        width: u32,
+        height: u32,
+        monitor: u32,
+    ) -> Result<Self, Error> {
+        let (_, device, queue) = gpu::request_device("shader")?;
+        let (width, height) = (width.max(1), height.max(1));
+
+        // Explicit layout, so user shader may leave any of the bindings unused
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("user shader"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("user shader"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("user shader"),
+            source: wgpu::ShaderSource::Wgsl(format!("{PRELUDE}\n{source}").into()),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("user shader"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: Some("waylock_vertex"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: None,
+                compilation_options: Default::default(),
+                targets: &[Some(FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        if let Some(err) = block_on(device.pop_error_scope()) {
+            return Err(Error::Compile(err));
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let black = [0, 0, 0, 255];
+        let (input_size, data): (_, &[u8]) = match input {
+            Some(image) if image.width > 0 && image.height > 0 => (
+                wgpu::Extent3d {
+                    width: image.width,
+                    height: image.height,
+                    depth_or_array_layers: 1,
+                },
+                bytemuck::cast_slice(&image.pixels),
+            ),
+            _ => (wgpu::Extent3d::default(), &black),
+        };
+
+        let background = device.create_texture_with_data(
+            &queue,
+            &wgpu::TextureDescriptor {
+                label: Some("background"),
+                size: input_size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            data,
+        );
+
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shader target"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let padded_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: u64::from(padded_row * height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let uniforms = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("shader uniforms"),
+            contents: &[0; 16],
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("background"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let view = background.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("user shader"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            bind_group,
+            uniforms,
+            target,
+            readback,
+            width,
+            height,
+            padded_row,
+            monitor,
+        })
+    }
+
+    /// Render a frame at `time` seconds
+    pub fn render(&self, time: f32) -> Option<blur::Image> {
+        let uniforms = [
+            (self.width as f32).to_bits(),
+            (self.height as f32).to_bits(),
+            time.to_bits(),
+            self.monitor,
+        ];
+        self.queue
+            .write_buffer(&self.uniforms, 0, bytemuck::cast_slice(&uniforms));
+
+        let view = self
+            .target
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("user shader"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        encoder.copy_texture_to_buffer(
+            self.target.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            self.target.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = self.readback.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+
+        if let Err(err) = self.device.poll(wgpu::PollType::wait_indefinitely()) {
+            log::warning!("failed to wait for GPU: {err}");
+            return None;
+        }
+
+        rx.recv().ok()?.ok()?;
+
+        let row = (self.width * 4) as usize;
+        let pixels = slice
+            .get_mapped_range()
+            .chunks(self.padded_row as usize)
+            .flat_map(|line| &line[..row])
+            .copied()
+            .collect::<Vec<u8>>();
+        self.readback.unmap();
+
+        Some(blur::Image {
+            width: self.width,
+            height: self.height,
+            pixels: bytemuck::cast_vec(pixels),
+        })
+    }
+}
+
+/// Read and compile shader to make sure it can be used
+pub fn check(path: &Path) -> Result<(), Error> {
+    let source = std::fs::read_to_string(path)?;
+    Shader::new(&source, None, 1, 1, 0).map(drop)
+}
@@ -0,0 +1,32 @@
+// Prepended to user shaders. User shader must define a single fragment entry
+// point taking `@location(0) uv: vec2<f32>` and returning `@location(0) vec4<f32>`.
+
+struct Waylock {
+    // Background size in pixels
+    resolution: vec2<f32>,
+    // Seconds since lock window appeared
+    time: f32,
+    // Index of the monitor
+    monitor: u32,
+}
+
+@group(0) @binding(0) var<uniform> waylock: Waylock;
+// Screenshot or image background, black if there is none
+@group(0) @binding(1) var background: texture_2d<f32>;
+@group(0) @binding(2) var background_sampler: sampler;
+
+struct WaylockVertex {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// Single triangle covering the whole target
+@vertex
+fn waylock_vertex(@builtin(vertex_index) index: u32) -> WaylockVertex {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: WaylockVertex;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
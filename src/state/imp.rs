@@ -0,0 +1,24 @@
+use std::cell::{Cell, RefCell};
+
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+#[derive(Debug, Default, glib::Properties)]
+#[properties(wrapper_type = super::LockState)]
+pub struct LockState {
+    /// When the session got locked, or preview started
+    #[property(get, set)]
+    pub locked_at: RefCell<Option<glib::DateTime>>,
+
+    /// Wrong passwords entered on any monitor
+    #[property(get, set)]
+    pub failed_attempts: Cell<u32>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for LockState {
+    const NAME: &str = "WaylockLockState";
+    type Type = super::LockState;
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for LockState {}
@@ -0,0 +1,80 @@
+mod imp;
+
+use gtk::glib;
+
+glib::wrapper! {
+    /// Lock state shared by windows of all monitors
+    pub struct LockState(ObjectSubclass<imp::LockState>);
+}
+
+impl Default for LockState {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl LockState {
+    /// Minutes since the session was locked as read off the clock, so they change
+    /// on minute boundaries together with it and add up with `locked_since`
+    pub fn locked_minutes(&self) -> Option<i64> {
+        let now = glib::DateTime::now_local().ok()?;
+        Some(clock_minutes(&self.locked_at()?, &now))
+    }
+
+    /// `14:02`
+    pub fn locked_since(&self) -> Option<glib::GString> {
+        self.locked_at()?.format("%H:%M").ok()
+    }
+
+    /// `Locked since 14:02 (35 min)`
+    pub fn describe(&self) -> Option<String> {
+        Some(format!(
+            "Locked since {} ({})",
+            self.locked_since()?,
+            format_minutes(self.locked_minutes()?)
+        ))
+    }
+
+    pub fn add_failed_attempt(&self) {
+        self.set_failed_attempts(self.failed_attempts() + 1);
+    }
+}
+
+/// Minute boundaries of the clock crossed between `since` and `now`
+fn clock_minutes(since: &glib::DateTime, now: &glib::DateTime) -> i64 {
+    now.to_unix().div_euclid(60) - since.to_unix().div_euclid(60)
+}
+
+/// `35 min`, `2 h 5 min`
+pub fn format_minutes(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes} min"),
+        (hours, 0) => format!("{hours} h"),
+        (hours, minutes) => format!("{hours} h {minutes} min"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: i32, minute: i32, seconds: f64) -> glib::DateTime {
+        glib::DateTime::from_utc(2024, 1, 15, hour, minute, seconds).unwrap()
+    }
+
+    #[test]
+    fn minutes_follow_the_clock() {
+        // Locked late in a minute, the clock moved on already
+        assert_eq!(clock_minutes(&at(14, 2, 50.0), &at(14, 3, 5.0)), 1);
+        assert_eq!(clock_minutes(&at(14, 2, 5.0), &at(14, 2, 59.0)), 0);
+        assert_eq!(clock_minutes(&at(14, 2, 59.0), &at(14, 37, 0.0)), 35);
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_minutes(0), "0 min");
+        assert_eq!(format_minutes(35), "35 min");
+        assert_eq!(format_minutes(60), "1 h");
+        assert_eq!(format_minutes(125), "2 h 5 min");
+    }
+}
@@ -0,0 +1,195 @@
+// Private message bus standing in for the system bus in tests, with stub services on it.
+// GIO keeps a single system bus connection per process, so all tests share one bus
+// and run one at a time, as the services they export have well-known names.
+
+use std::{
+    future::Future,
+    sync::{Mutex, OnceLock, PoisonError},
+};
+
+use gtk::{gio, glib, prelude::*};
+
+/// Address of the bus, started on first use and stopped when the test process exits
+fn address() -> &'static str {
+    static ADDRESS: OnceLock<String> = OnceLock::new();
+
+    ADDRESS.get_or_init(|| {
+        let bus = gio::TestDBus::new(gio::TestDBusFlags::NONE);
+        bus.up();
+
+        let address = bus
+            .bus_address()
+            .expect("test bus has no address")
+            .to_string();
+
+        // SAFETY: set once, before any test connects to the system bus
+        unsafe { std::env::set_var("DBUS_SYSTEM_BUS_ADDRESS", &address) };
+
+        // Watcher process of `TestDBus` stops the daemon once we are gone
+        std::mem::forget(bus);
+
+        address
+    })
+}
+
+/// Run `future` on a main context of its own, stub services answer while it waits
+pub fn run<F: Future>(future: F) -> F::Output {
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    // A failed test must not fail the others
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    address();
+
+    glib::MainContext::new().block_on(future)
+}
+
+/// Stub of a service, owns its name until dropped
+pub struct Service {
+    connection: gio::DBusConnection,
+    name: &'static str,
+    path: &'static str,
+    interface: &'static str,
+    registrations: Vec<gio::RegistrationId>,
+}
+
+/// Export `interface` described by introspection `xml` at `path` on `connection`
+fn register(
+    connection: &gio::DBusConnection,
+    path: &str,
+    interface: &str,
+    xml: &str,
+    methods: impl Fn(&str, glib::Variant) -> Result<glib::Variant, glib::Error> + 'static,
+    properties: impl Fn(&str) -> glib::Variant + 'static,
+) -> gio::RegistrationId {
+    let info = gio::DBusNodeInfo::for_xml(xml)
+        .expect("invalid introspection")
+        .lookup_interface(interface)
+        .expect("interface is not in introspection");
+
+    connection
+        .register_object(path, &info)
+        .method_call(move |_, _, _, _, method, parameters, invocation| {
+            invocation.return_result(methods(method, parameters).map(Some));
+        })
+        .property(move |_, _, _, _, property| properties(property))
+        .build()
+        .expect("failed to export stub")
+}
+
+impl Service {
+    /// Export `interface` described by introspection `xml` at `path` and take `name`.
+    /// Must be created inside `run()`, calls are answered on its main context.
+    pub fn new(
+        name: &'static str,
+        path: &'static str,
+        interface: &'static str,
+        xml: &str,
+        methods: impl Fn(&str, glib::Variant) -> Result<glib::Variant, glib::Error> + 'static,
+        properties: impl Fn(&str) -> glib::Variant + 'static,
+    ) -> Self {
+        let connection = gio::DBusConnection::for_address_sync(
+            address(),
+            gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+                | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+            None,
+            gio::Cancellable::NONE,
+        )
+        .expect("failed to connect to test bus");
+
+        let registration = register(&connection, path, interface, xml, methods, properties);
+
+        // 4 is DO_NOT_QUEUE, 1 is PRIMARY_OWNER
+        let reply = connection
+            .call_sync(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                "org.freedesktop.DBus",
+                "RequestName",
+                Some(&(name, 4u32).to_variant()),
+                None,
+                gio::DBusCallFlags::NONE,
+                -1,
+                gio::Cancellable::NONE,
+            )
+            .expect("failed to request name");
+        assert_eq!(reply.get::<(u32,)>(), Some((1,)), "{name} is taken");
+
+        Self {
+            connection,
+            name,
+            path,
+            interface,
+            registrations: vec![registration],
+        }
+    }
+
+    /// Export one more object without methods, e.g. one the main object refers to
+    pub fn export(
+        &mut self,
+        path: &str,
+        interface: &str,
+        xml: &str,
+        properties: impl Fn(&str) -> glib::Variant + 'static,
+    ) {
+        let registration = register(
+            &self.connection,
+            path,
+            interface,
+            xml,
+            |method, _| unreachable!("{method} is not stubbed"),
+            properties,
+        );
+        self.registrations.push(registration);
+    }
+
+    /// Tell proxies that `changed` properties have new values
+    pub fn properties_changed(&self, changed: &[(&str, glib::Variant)]) {
+        let changed = changed
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect::<std::collections::HashMap<_, _>>();
+
+        self.connection
+            .emit_signal(
+                None,
+                self.path,
+                "org.freedesktop.DBus.Properties",
+                "PropertiesChanged",
+                Some(&(self.interface, changed, Vec::<String>::new()).to_variant()),
+            )
+            .expect("failed to emit PropertiesChanged");
+    }
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        for registration in self.registrations.drain(..) {
+            let _ = self.connection.unregister_object(registration);
+        }
+
+        let _ = self.connection.call_sync(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "ReleaseName",
+            Some(&(self.name,).to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+            gio::Cancellable::NONE,
+        );
+    }
+}
+
+/// Wait until `condition` holds, iterating the main context in between
+pub async fn wait_for(mut condition: impl FnMut() -> bool) {
+    for _ in 0..200 {
+        if condition() {
+            return;
+        }
+
+        glib::timeout_future(std::time::Duration::from_millis(10)).await;
+    }
+
+    panic!("timed out waiting for the bus");
+}
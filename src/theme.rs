@@ -0,0 +1,261 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use gtk::{gio, glib, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, log};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    /// Follow `color-scheme` of the settings portal, light if it has no preference
+    #[default]
+    System,
+    Dark,
+    Light,
+    /// Dark at night, see `day-start`, `night-start`, `latitude` and `longitude`
+    Time,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Dark,
+    Light,
+}
+
+impl Variant {
+    pub const fn is_dark(self) -> bool {
+        matches!(self, Self::Dark)
+    }
+
+    /// `style-dark.css` or `style-light.css` next to `style`
+    pub fn style(self, style: &Path) -> PathBuf {
+        style.with_file_name(match self {
+            Self::Dark => "style-dark.css",
+            Self::Light => "style-light.css",
+        })
+    }
+}
+
+/// `org.freedesktop.appearance color-scheme`: 0 no preference, 1 dark, 2 light
+fn portal_color_scheme() -> Option<Variant> {
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)
+        .map_err(|err| log::info!("no session bus: {err}"))
+        .ok()?;
+
+    let call = |method: &str| {
+        connection.call_sync(
+            Some("org.freedesktop.portal.Desktop"),
+            "/org/freedesktop/portal/desktop",
+            "org.freedesktop.portal.Settings",
+            method,
+            Some(&("org.freedesktop.appearance", "color-scheme").to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            1000,
+            gio::Cancellable::NONE,
+        )
+    };
+
+    // `Read` of older portals wraps the value in one more variant
+    let value = match call("ReadOne") {
+        Ok(reply) => reply.child_value(0).as_variant()?,
+        Err(_) => call("Read")
+            .map_err(|err| log::info!("failed to read color scheme: {err}"))
+            .ok()?
+            .child_value(0)
+            .as_variant()?
+            .as_variant()?,
+    };
+
+    match value.get::<u32>()? {
+        1 => Some(Variant::Dark),
+        2 => Some(Variant::Light),
+        _ => None,
+    }
+}
+
+/// Parse `HH:MM` into minutes since midnight
+fn parse_time(s: &str) -> Option<f64> {
+    let (hours, minutes) = s.split_once(':')?;
+    let (hours, minutes) = (
+        hours.trim().parse::<u32>().ok()?,
+        minutes.trim().parse::<u32>().ok()?,
+    );
+
+    (hours < 24 && minutes < 60).then_some(f64::from(hours * 60 + minutes))
+}
+
+/// Sunrise and sunset in minutes since UTC midnight, NOAA approximation.
+/// `Err(true)` is polar day, `Err(false)` is polar night.
+fn sun_times(day_of_year: i32, latitude: f64, longitude: f64) -> Result<(f64, f64), bool> {
+    use std::f64::consts::PI;
+
+    let gamma = 2.0 * PI / 365.0 * (f64::from(day_of_year) - 0.5);
+    let equation_of_time = 229.18
+        * (0.000_075 + 0.001_868 * gamma.cos()
+            - 0.032_077 * gamma.sin()
+            - 0.014_615 * (2.0 * gamma).cos()
+            - 0.040_849 * (2.0 * gamma).sin());
+    let declination = 0.006_918 - 0.399_912 * gamma.cos() + 0.070_257 * gamma.sin()
+        - 0.006_758 * (2.0 * gamma).cos()
+        + 0.000_907 * (2.0 * gamma).sin()
+        - 0.002_697 * (3.0 * gamma).cos()
+        + 0.001_48 * (3.0 * gamma).sin();
+
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = 90.833_f64.to_radians().cos() / (latitude.cos() * declination.cos())
+        - latitude.tan() * declination.tan();
+
+    if cos_hour_angle < -1.0 {
+        return Err(true);
+    }
+    if cos_hour_angle > 1.0 {
+        return Err(false);
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+
+    Ok((
+        720.0 - 4.0 * (longitude + hour_angle) - equation_of_time,
+        720.0 - 4.0 * (longitude - hour_angle) - equation_of_time,
+    ))
+}
+
+/// Whether `now` is between `start` and `end`, all in minutes, range may wrap midnight
+fn within(now: f64, start: f64, end: f64) -> bool {
+    let (now, start, end) = (
+        now.rem_euclid(1440.0),
+        start.rem_euclid(1440.0),
+        end.rem_euclid(1440.0),
+    );
+
+    if start <= end {
+        (start..end).contains(&now)
+    } else {
+        now >= start || now < end
+    }
+}
+
+fn time_of_day(config: &Config) -> Variant {
+    let Ok(now) = glib::DateTime::now_local() else {
+        return Variant::Light;
+    };
+
+    let minutes = f64::from(now.hour() * 60 + now.minute());
+
+    let day = match config.get_coordinates() {
+        Some((latitude, longitude)) => {
+            let offset = now.utc_offset().as_minutes() as f64;
+
+            match sun_times(now.day_of_year(), latitude, longitude) {
+                Ok((sunrise, sunset)) => within(minutes, sunrise + offset, sunset + offset),
+                Err(polar_day) => polar_day,
+            }
+        }
+        None => {
+            let start = parse_time(config.get_day_start());
+            let end = parse_time(config.get_night_start());
+
+            match (start, end) {
+                (Some(start), Some(end)) => within(minutes, start, end),
+                _ => {
+                    log::warning!("invalid day-start or night-start, expected HH:MM");
+                    true
+                }
+            }
+        }
+    };
+
+    if day { Variant::Light } else { Variant::Dark }
+}
+
+/// Decide whether we are dark or light right now
+pub fn variant(config: &Config) -> Variant {
+    match config.get_color_scheme() {
+        ColorScheme::Dark => Variant::Dark,
+        ColorScheme::Light => Variant::Light,
+        ColorScheme::Time => time_of_day(config),
+        ColorScheme::System => portal_color_scheme().unwrap_or(Variant::Light),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minutes since midnight of `HH:MM`
+    const fn hm(hours: u32, minutes: u32) -> f64 {
+        (hours * 60 + minutes) as f64
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= 3.0,
+            "{actual} is more than 3 minutes from {expected}"
+        );
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time("07:30"), Some(hm(7, 30)));
+        assert_eq!(parse_time("0:00"), Some(0.0));
+        assert_eq!(parse_time(" 23 : 59 "), Some(hm(23, 59)));
+        assert_eq!(parse_time("24:00"), None);
+        assert_eq!(parse_time("12:60"), None);
+        assert_eq!(parse_time("12"), None);
+        assert_eq!(parse_time("-1:00"), None);
+        assert_eq!(parse_time("noon"), None);
+    }
+
+    #[test]
+    fn greenwich() {
+        // Midsummer, 03:43 and 20:21 UTC
+        let (sunrise, sunset) = sun_times(172, 51.4769, 0.0).unwrap();
+        assert_near(sunrise, hm(3, 43));
+        assert_near(sunset, hm(20, 21));
+
+        // Midwinter, 08:04 and 15:53 UTC
+        let (sunrise, sunset) = sun_times(355, 51.4769, 0.0).unwrap();
+        assert_near(sunrise, hm(8, 4));
+        assert_near(sunset, hm(15, 53));
+    }
+
+    #[test]
+    fn east_of_greenwich() {
+        // Berlin at midsummer, 02:43 and 19:33 UTC
+        let (sunrise, sunset) = sun_times(172, 52.52, 13.405).unwrap();
+        assert_near(sunrise, hm(2, 43));
+        assert_near(sunset, hm(19, 33));
+    }
+
+    #[test]
+    fn polar() {
+        // Longyearbyen
+        assert_eq!(sun_times(172, 78.22, 15.65), Err(true));
+        assert_eq!(sun_times(355, 78.22, 15.65), Err(false));
+
+        // Seasons are the other way round in the south
+        assert_eq!(sun_times(172, -78.22, 15.65), Err(false));
+        assert_eq!(sun_times(355, -78.22, 15.65), Err(true));
+    }
+
+    #[test]
+    fn ranges() {
+        assert!(within(hm(12, 0), hm(7, 0), hm(19, 0)));
+        assert!(within(hm(7, 0), hm(7, 0), hm(19, 0)));
+        assert!(!within(hm(19, 0), hm(7, 0), hm(19, 0)));
+        assert!(!within(hm(3, 0), hm(7, 0), hm(19, 0)));
+
+        // Wrapping midnight
+        assert!(within(hm(23, 0), hm(22, 0), hm(6, 0)));
+        assert!(within(hm(1, 0), hm(22, 0), hm(6, 0)));
+        assert!(!within(hm(12, 0), hm(22, 0), hm(6, 0)));
+
+        // Sun times shifted by UTC offset out of 0..1440
+        assert!(within(hm(1, 0), hm(20, 0) - 1440.0, hm(30, 0)));
+        assert!(within(hm(23, 0), hm(20, 0), hm(30, 0)));
+        assert!(!within(hm(12, 0), hm(20, 0), hm(30, 0)));
+    }
+}
@@ -0,0 +1,420 @@
+use gtk::{gio, glib, prelude::*};
+
+pub const DESTINATION: &str = "org.freedesktop.UPower";
+/// Composite of all batteries, as shown by desktop panels
+pub const DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+pub const DEVICE: &str = "org.freedesktop.UPower.Device";
+
+const TYPE_BATTERY: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Unknown,
+    Charging,
+    Discharging,
+    Empty,
+    FullyCharged,
+    PendingCharge,
+    PendingDischarge,
+}
+
+impl From<u32> for State {
+    fn from(state: u32) -> Self {
+        match state {
+            1 => Self::Charging,
+            2 => Self::Discharging,
+            3 => Self::Empty,
+            4 => Self::FullyCharged,
+            5 => Self::PendingCharge,
+            6 => Self::PendingDischarge,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// `WarningLevel` of the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WarningLevel {
+    None,
+    Low,
+    Critical,
+    /// Action is about to be taken, e.g. hibernation
+    Action,
+}
+
+impl From<u32> for WarningLevel {
+    fn from(level: u32) -> Self {
+        match level {
+            3 => Self::Low,
+            4 => Self::Critical,
+            5 => Self::Action,
+            _ => Self::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Battery {
+    pub percentage: f64,
+    pub state: State,
+    /// Seconds, 0 when unknown
+    pub time_to_empty: i64,
+    /// Seconds, 0 when unknown
+    pub time_to_full: i64,
+    pub warning_level: WarningLevel,
+    pub icon_name: String,
+}
+
+impl Battery {
+    /// Read cached properties of the display device, `None` without batteries
+    pub fn from_proxy(proxy: &gio::DBusProxy) -> Option<Self> {
+        fn property<T: glib::variant::FromVariant>(
+            proxy: &gio::DBusProxy,
+            name: &str,
+        ) -> Option<T> {
+            proxy.cached_property(name)?.get()
+        }
+
+        if !property::<bool>(proxy, "IsPresent")? || property::<u32>(proxy, "Type")? != TYPE_BATTERY
+        {
+            return None;
+        }
+
+        Some(Self {
+            percentage: property(proxy, "Percentage").unwrap_or_default(),
+            state: property::<u32>(proxy, "State").unwrap_or_default().into(),
+            time_to_empty: property(proxy, "TimeToEmpty").unwrap_or_default(),
+            time_to_full: property(proxy, "TimeToFull").unwrap_or_default(),
+            warning_level: property::<u32>(proxy, "WarningLevel")
+                .unwrap_or_default()
+                .into(),
+            icon_name: property(proxy, "IconName").unwrap_or_default(),
+        })
+    }
+
+    /// "2 h 15 min", `None` when unknown
+    pub fn format_time(seconds: i64) -> Option<String> {
+        let minutes = seconds / 60;
+
+        match (minutes / 60, minutes % 60) {
+            (0, 0) => None,
+            (0, minutes) => Some(format!("{minutes} min")),
+            (hours, 0) => Some(format!("{hours} h")),
+            (hours, minutes) => Some(format!("{hours} h {minutes} min")),
+        }
+    }
+
+    /// "87%, 2 h 15 min left"
+    pub fn describe(&self) -> String {
+        let percentage = format!("{:.0}%", self.percentage);
+
+        match self.state {
+            State::Charging | State::PendingCharge => match Self::format_time(self.time_to_full) {
+                Some(time) => format!("{percentage}, charging, full in {time}"),
+                None => format!("{percentage}, charging"),
+            },
+            State::Discharging | State::PendingDischarge => {
+                match Self::format_time(self.time_to_empty) {
+                    Some(time) => format!("{percentage}, {time} left"),
+                    None => percentage,
+                }
+            }
+            State::FullyCharged => format!("{percentage}, fully charged"),
+            State::Empty | State::Unknown => percentage,
+        }
+    }
+}
+
+/// Warns once per level as the charge drops, and again after charging
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Warnings {
+    warned: Option<WarningLevel>,
+}
+
+impl Warnings {
+    /// Message for `battery` if it reached a level not warned about yet
+    pub fn check(&mut self, battery: &Battery) -> Option<String> {
+        if battery.warning_level == WarningLevel::None {
+            // Charging again, warn when it runs low next time
+            self.warned = None;
+            return None;
+        }
+
+        if self.warned >= Some(battery.warning_level) {
+            return None;
+        }
+
+        self.warned = Some(battery.warning_level);
+
+        let text = battery.describe();
+        Some(match battery.warning_level {
+            WarningLevel::Low => format!("Battery is low: {text}"),
+            _ => format!("Battery is critically low: {text}"),
+        })
+    }
+}
+
+/// Proxy of the display device, caching its properties and emitting
+/// `g-properties-changed`. Without UPower it is created all the same,
+/// it just has no properties.
+pub async fn display_device() -> Result<gio::DBusProxy, glib::Error> {
+    gio::DBusProxy::for_bus_future(
+        gio::BusType::System,
+        gio::DBusProxyFlags::NONE,
+        None,
+        DESTINATION,
+        DISPLAY_DEVICE,
+        DEVICE,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+    use super::*;
+    use crate::test_bus::{self, Service};
+
+    const INTROSPECTION: &str = r#"<node><interface name="org.freedesktop.UPower.Device">
+        <property name="IsPresent" type="b" access="read"/>
+        <property name="Type" type="u" access="read"/>
+        <property name="Percentage" type="d" access="read"/>
+        <property name="State" type="u" access="read"/>
+        <property name="TimeToEmpty" type="x" access="read"/>
+        <property name="TimeToFull" type="x" access="read"/>
+        <property name="WarningLevel" type="u" access="read"/>
+        <property name="IconName" type="s" access="read"/>
+    </interface></node>"#;
+
+    type Properties = Rc<RefCell<HashMap<&'static str, glib::Variant>>>;
+
+    /// Discharging battery at 87%
+    fn properties() -> Properties {
+        Rc::new(RefCell::new(HashMap::from([
+            ("IsPresent", true.to_variant()),
+            ("Type", TYPE_BATTERY.to_variant()),
+            ("Percentage", 87.0f64.to_variant()),
+            ("State", 2u32.to_variant()),
+            ("TimeToEmpty", 8100i64.to_variant()),
+            ("TimeToFull", 0i64.to_variant()),
+            ("WarningLevel", 1u32.to_variant()),
+            ("IconName", "battery-full-symbolic".to_variant()),
+        ])))
+    }
+
+    fn display_device_stub(properties: &Properties) -> Service {
+        let properties = properties.clone();
+
+        Service::new(
+            DESTINATION,
+            DISPLAY_DEVICE,
+            DEVICE,
+            INTROSPECTION,
+            |_, _| unreachable!("display device stub has no methods"),
+            move |name| properties.borrow()[name].clone(),
+        )
+    }
+
+    /// Change properties of the stub and wait for `proxy` to see them
+    async fn change(
+        service: &Service,
+        properties: &Properties,
+        proxy: &gio::DBusProxy,
+        changed: &[(&'static str, glib::Variant)],
+    ) {
+        properties.borrow_mut().extend(changed.iter().cloned());
+        service.properties_changed(changed);
+
+        test_bus::wait_for(|| {
+            changed
+                .iter()
+                .all(|(name, value)| proxy.cached_property(name).as_ref() == Some(value))
+        })
+        .await;
+    }
+
+    fn battery(state: State, percentage: f64) -> Battery {
+        Battery {
+            percentage,
+            state,
+            time_to_empty: 0,
+            time_to_full: 0,
+            warning_level: WarningLevel::None,
+            icon_name: String::new(),
+        }
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(Battery::format_time(0), None);
+        assert_eq!(Battery::format_time(59), None);
+        assert_eq!(Battery::format_time(60).as_deref(), Some("1 min"));
+        assert_eq!(Battery::format_time(3600).as_deref(), Some("1 h"));
+        assert_eq!(Battery::format_time(3659).as_deref(), Some("1 h"));
+        assert_eq!(Battery::format_time(8100).as_deref(), Some("2 h 15 min"));
+    }
+
+    #[test]
+    fn descriptions() {
+        let discharging = Battery {
+            time_to_empty: 8100,
+            ..battery(State::Discharging, 87.4)
+        };
+        assert_eq!(discharging.describe(), "87%, 2 h 15 min left");
+        assert_eq!(battery(State::Discharging, 87.6).describe(), "88%");
+        assert_eq!(battery(State::PendingDischarge, 50.0).describe(), "50%");
+
+        let charging = Battery {
+            time_to_full: 1800,
+            ..battery(State::Charging, 40.0)
+        };
+        assert_eq!(charging.describe(), "40%, charging, full in 30 min");
+        assert_eq!(
+            battery(State::PendingCharge, 40.0).describe(),
+            "40%, charging"
+        );
+
+        assert_eq!(
+            battery(State::FullyCharged, 100.0).describe(),
+            "100%, fully charged"
+        );
+        assert_eq!(battery(State::Empty, 0.0).describe(), "0%");
+        assert_eq!(battery(State::Unknown, 10.0).describe(), "10%");
+    }
+
+    #[test]
+    fn no_battery() {
+        test_bus::run(async {
+            // Desktop on mains, the display device is line power
+            let properties = properties();
+            properties.borrow_mut().insert("Type", 1u32.to_variant());
+            let service = display_device_stub(&properties);
+
+            let proxy = display_device().await.unwrap();
+            assert_eq!(Battery::from_proxy(&proxy), None);
+
+            change(
+                &service,
+                &properties,
+                &proxy,
+                &[
+                    ("Type", TYPE_BATTERY.to_variant()),
+                    ("IsPresent", false.to_variant()),
+                ],
+            )
+            .await;
+            assert_eq!(Battery::from_proxy(&proxy), None);
+
+            change(
+                &service,
+                &properties,
+                &proxy,
+                &[("IsPresent", true.to_variant())],
+            )
+            .await;
+            assert!(Battery::from_proxy(&proxy).is_some());
+        });
+    }
+
+    #[test]
+    fn display_device_updates() {
+        test_bus::run(async {
+            let properties = properties();
+            let service = display_device_stub(&properties);
+
+            let proxy = display_device().await.unwrap();
+            assert_eq!(
+                Battery::from_proxy(&proxy),
+                Some(Battery {
+                    percentage: 87.0,
+                    state: State::Discharging,
+                    time_to_empty: 8100,
+                    time_to_full: 0,
+                    warning_level: WarningLevel::None,
+                    icon_name: "battery-full-symbolic".into(),
+                })
+            );
+
+            change(
+                &service,
+                &properties,
+                &proxy,
+                &[
+                    ("State", 1u32.to_variant()),
+                    ("TimeToFull", 1800i64.to_variant()),
+                ],
+            )
+            .await;
+            assert_eq!(
+                Battery::from_proxy(&proxy).unwrap().describe(),
+                "87%, charging, full in 30 min"
+            );
+
+            change(
+                &service,
+                &properties,
+                &proxy,
+                &[
+                    ("State", 4u32.to_variant()),
+                    ("Percentage", 100.0f64.to_variant()),
+                ],
+            )
+            .await;
+            assert_eq!(
+                Battery::from_proxy(&proxy).unwrap().describe(),
+                "100%, fully charged"
+            );
+        });
+    }
+
+    #[test]
+    fn one_warning_per_level() {
+        test_bus::run(async {
+            let properties = properties();
+            let service = display_device_stub(&properties);
+            let proxy = display_device().await.unwrap();
+            let mut warnings = Warnings::default();
+
+            let mut messages = Vec::new();
+            for (percentage, level) in [
+                (20.0f64, 1u32),
+                (10.0, 3),
+                (9.0, 3),
+                (8.0, 3),
+                (5.0, 4),
+                (4.0, 4),
+                (2.0, 5),
+                (1.0, 5),
+                // Plugged in and charged a bit, then unplugged again
+                (12.0, 1),
+                (10.0, 3),
+            ] {
+                change(
+                    &service,
+                    &properties,
+                    &proxy,
+                    &[
+                        ("Percentage", percentage.to_variant()),
+                        ("TimeToEmpty", 0i64.to_variant()),
+                        ("WarningLevel", level.to_variant()),
+                    ],
+                )
+                .await;
+
+                let battery = Battery::from_proxy(&proxy).unwrap();
+                messages.extend(warnings.check(&battery));
+            }
+
+            assert_eq!(
+                messages,
+                [
+                    "Battery is low: 10%",
+                    "Battery is critically low: 5%",
+                    "Battery is critically low: 2%",
+                    "Battery is low: 10%",
+                ]
+            );
+        });
+    }
+}
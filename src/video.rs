@@ -0,0 +1,64 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::log;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Decoder {
+    /// Let GStreamer choose
+    #[default]
+    Auto,
+    /// Prefer VA-API, software if there is no render device
+    Vaapi,
+    Software,
+}
+
+/// VA-API decoders of both `va` and legacy `vaapi` GStreamer plugins
+const VA_DECODERS: &[&str] = &[
+    "vah264dec",
+    "vah265dec",
+    "vavp8dec",
+    "vavp9dec",
+    "vaav1dec",
+    "vampeg2dec",
+    "vaapih264dec",
+    "vaapih265dec",
+    "vaapivp8dec",
+    "vaapivp9dec",
+    "vaapiav1dec",
+    "vaapimpeg2dec",
+];
+
+fn render_node_available() -> bool {
+    std::fs::read_dir("/dev/dri").is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|entry| entry.file_name().to_string_lossy().starts_with("renderD"))
+    })
+}
+
+/// Rank VA-API decoders for GTK's GStreamer media backend.
+/// Must be called before GStreamer is initialized and before any thread is spawned.
+pub fn configure_decoder(decoder: Decoder) {
+    let rank = match decoder {
+        Decoder::Auto => return,
+        Decoder::Vaapi if !render_node_available() => {
+            log::info!("no DRM render node, using software video decoding");
+            "NONE"
+        }
+        Decoder::Vaapi => "MAX",
+        Decoder::Software => "NONE",
+    };
+
+    // User's own ranks come last so they win
+    let ranks = VA_DECODERS
+        .iter()
+        .map(|decoder| format!("{decoder}:{rank}"))
+        .chain(std::env::var("GST_PLUGIN_FEATURE_RANK").ok())
+        .filter(|rank| !rank.is_empty())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    unsafe { std::env::set_var("GST_PLUGIN_FEATURE_RANK", ranks) };
+}
@@ -0,0 +1,125 @@
+use std::cell::{Cell, RefCell};
+
+use gtk::{
+    Align,
+    Box,
+    Frame,
+    Image,
+    Label,
+    Orientation,
+    gio,
+    glib,
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use crate::{log, messages::MessageWindow, upower};
+
+#[derive(Debug, Default, glib::Properties)]
+#[properties(wrapper_type = super::Battery)]
+pub struct Battery {
+    #[property(get, set, construct_only)]
+    pub feed: RefCell<Option<MessageWindow>>,
+
+    pub icon: RefCell<Image>,
+    pub label: RefCell<Label>,
+    /// Kept alive for `g-properties-changed`
+    pub proxy: RefCell<Option<gio::DBusProxy>>,
+    /// Warn once per level, not on every update
+    pub warnings: Cell<upower::Warnings>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Battery {
+    const NAME: &str = "Battery";
+    type Type = super::Battery;
+    type ParentType = Box;
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for Battery {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_visible(false);
+
+        let content = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .halign(Align::Center)
+            .spacing(4)
+            .margin_top(4)
+            .margin_start(4)
+            .margin_end(4)
+            .margin_bottom(4)
+            .build();
+
+        let icon = Image::new();
+        let label = Label::new(None);
+        content.append(&icon);
+        content.append(&label);
+
+        obj.append(
+            &Frame::builder()
+                .child(&content)
+                .css_classes(["bubble-frame", "battery"])
+                .build(),
+        );
+
+        *self.icon.borrow_mut() = icon;
+        *self.label.borrow_mut() = label;
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = battery)]
+            self,
+            async move {
+                let proxy = match upower::display_device().await {
+                    Ok(proxy) => proxy,
+                    Err(err) => {
+                        log::info!("UPower is not available: {err}");
+                        return;
+                    }
+                };
+
+                proxy.connect_g_properties_changed(glib::clone!(
+                    #[weak]
+                    battery,
+                    move |proxy, _, _| battery.update(proxy)
+                ));
+
+                battery.update(&proxy);
+                *battery.proxy.borrow_mut() = Some(proxy);
+            }
+        ));
+    }
+}
+
+impl WidgetImpl for Battery {}
+impl BoxImpl for Battery {}
+
+impl Battery {
+    fn update(&self, proxy: &gio::DBusProxy) {
+        let Some(battery) = upower::Battery::from_proxy(proxy) else {
+            self.obj().set_visible(false);
+            return;
+        };
+
+        self.icon.borrow().set_icon_name(Some(&battery.icon_name));
+        self.label.borrow().set_text(&battery.describe());
+        self.obj().set_visible(true);
+
+        let mut warnings = self.warnings.get();
+        let message = warnings.check(&battery);
+        self.warnings.set(warnings);
+
+        let Some(message) = message else {
+            return;
+        };
+
+        log::warning!("{message}");
+
+        if let Some(feed) = self.feed.borrow().as_ref() {
+            feed.add_message(&message);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+mod imp;
+
+use gtk::{Accessible, Box, Buildable, ConstraintTarget, Orientable, Widget, glib};
+
+use crate::messages::MessageWindow;
+
+glib::wrapper! {
+    pub struct Battery(ObjectSubclass<imp::Battery>)
+        @extends Widget, Box,
+        @implements Accessible, Buildable, ConstraintTarget, Orientable;
+}
+
+impl Battery {
+    /// Charge of the batteries from UPower, hidden without batteries.
+    /// Low battery warnings go to `feed`.
+    pub fn new(feed: &MessageWindow) -> Self {
+        glib::Object::builder().property("feed", feed).build()
+    }
+}
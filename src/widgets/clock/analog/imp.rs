@@ -0,0 +1,301 @@
+use std::{
+    cell::{Cell, RefCell},
+    f32::consts::PI,
+    time::Duration,
+};
+
+use gtk::{
+    Box,
+    TickCallbackId,
+    Widget,
+    gdk,
+    glib,
+    graphene,
+    gsk,
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use super::super::resync::Resync;
+
+#[derive(Debug, Default, glib::Properties)]
+#[properties(wrapper_type = super::AnalogClock)]
+pub struct AnalogClock {
+    /// Show seconds hand, it moves smoothly
+    #[property(get, set, construct, default = true)]
+    pub seconds: Cell<bool>,
+
+    /// Invisible CSS nodes giving colors to hands and ticks
+    pub parts: RefCell<Vec<Widget>>,
+    /// Redraws every frame for smooth seconds hand
+    pub tick: RefCell<Option<TickCallbackId>>,
+    /// Without seconds hand we redraw once a minute
+    pub source: RefCell<Option<glib::SourceId>>,
+    /// Exists while mapped
+    pub resync: RefCell<Option<Resync>>,
+}
+
+#[derive(Clone, Copy)]
+enum Part {
+    HourHand,
+    MinuteHand,
+    SecondHand,
+    HourTick,
+    MinuteTick,
+}
+
+impl Part {
+    const ALL: [Self; 5] = [
+        Self::HourHand,
+        Self::MinuteHand,
+        Self::SecondHand,
+        Self::HourTick,
+        Self::MinuteTick,
+    ];
+
+    const fn css(self) -> (&'static str, &'static str) {
+        match self {
+            Self::HourHand => ("hand", "hour"),
+            Self::MinuteHand => ("hand", "minute"),
+            Self::SecondHand => ("hand", "second"),
+            Self::HourTick => ("tick", "hour"),
+            Self::MinuteTick => ("tick", "minute"),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AnalogClock {
+    const NAME: &str = "AnalogClock";
+    type Type = super::AnalogClock;
+    type ParentType = Widget;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_css_name("analog-clock");
+        klass.set_accessible_role(gtk::AccessibleRole::Img);
+    }
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for AnalogClock {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+
+        *self.parts.borrow_mut() = Part::ALL
+            .into_iter()
+            .map(|part| {
+                let (name, class) = part.css();
+                let widget = Box::builder()
+                    .css_name(name)
+                    .css_classes([class])
+                    .build()
+                    .upcast::<Widget>();
+                widget.set_parent(&*obj);
+                widget
+            })
+            .collect();
+
+        obj.connect_seconds_notify(|clock| {
+            if clock.is_mapped() {
+                clock.imp().restart();
+            }
+        });
+    }
+
+    fn dispose(&self) {
+        self.stop();
+
+        for part in self.parts.take() {
+            part.unparent();
+        }
+    }
+}
+
+impl WidgetImpl for AnalogClock {
+    // Same as the digital clock, unmapped clock does not tick
+    fn map(&self) {
+        self.parent_map();
+
+        self.restart();
+        *self.resync.borrow_mut() = Some(Resync::new(glib::clone!(
+            #[weak(rename_to = clock)]
+            self,
+            move || clock.restart()
+        )));
+    }
+
+    fn unmap(&self) {
+        self.stop();
+        self.parent_unmap();
+    }
+
+    fn measure(&self, _orientation: gtk::Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+        // Size comes from CSS `min-width` and `min-height`
+        (0, 0, -1, -1)
+    }
+
+    fn size_allocate(&self, _width: i32, _height: i32, _baseline: i32) {
+        // Parts are never drawn, they only need an allocation to be valid
+        for part in self.parts.borrow().iter() {
+            part.allocate(0, 0, -1, None);
+        }
+    }
+
+    fn snapshot(&self, snapshot: &gtk::Snapshot) {
+        let Ok(now) = glib::DateTime::now_local() else {
+            return;
+        };
+
+        let obj = self.obj();
+        let parts = self.parts.borrow();
+        let color = |part: Part| parts[part as usize].color();
+
+        // Logical pixels, GSK renders paths at the device scale so fractional scales stay sharp
+        let (width, height) = (obj.width() as f32, obj.height() as f32);
+        let center = graphene::Point::new(width / 2.0, height / 2.0);
+        let radius = width.min(height) / 2.0;
+
+        let seconds = now.seconds() as f32;
+        // Without seconds hand the minute hand only moves when we redraw
+        let minutes = if self.seconds.get() {
+            now.minute() as f32 + seconds / 60.0
+        } else {
+            now.minute() as f32
+        };
+        let hours = (now.hour() % 12) as f32 + minutes / 60.0;
+
+        for i in 0..60 {
+            let (part, length, width) = if i % 5 == 0 {
+                (Part::HourTick, 0.15, 0.03)
+            } else {
+                (Part::MinuteTick, 0.06, 0.015)
+            };
+
+            line(
+                snapshot,
+                &center,
+                i as f32 / 60.0,
+                radius * (0.9 - length),
+                radius * 0.9,
+                radius * width,
+                &color(part),
+            );
+        }
+
+        line(
+            snapshot,
+            &center,
+            hours / 12.0,
+            -radius * 0.1,
+            radius * 0.5,
+            radius * 0.06,
+            &color(Part::HourHand),
+        );
+        line(
+            snapshot,
+            &center,
+            minutes / 60.0,
+            -radius * 0.1,
+            radius * 0.75,
+            radius * 0.04,
+            &color(Part::MinuteHand),
+        );
+
+        if self.seconds.get() {
+            let color = color(Part::SecondHand);
+
+            line(
+                snapshot,
+                &center,
+                seconds / 60.0,
+                -radius * 0.15,
+                radius * 0.85,
+                radius * 0.015,
+                &color,
+            );
+
+            let dot = gsk::PathBuilder::new();
+            dot.add_circle(&center, radius * 0.035);
+            snapshot.append_fill(&dot.to_path(), gsk::FillRule::Winding, &color);
+        }
+    }
+}
+
+impl AnalogClock {
+    /// Redraw now and wait for the next change
+    fn restart(&self) {
+        self.stop_timers();
+
+        let obj = self.obj();
+        obj.queue_draw();
+
+        if self.seconds.get() {
+            *self.tick.borrow_mut() = Some(obj.add_tick_callback(|clock, _| {
+                clock.queue_draw();
+                glib::ControlFlow::Continue
+            }));
+        } else {
+            self.schedule();
+        }
+    }
+
+    fn stop_timers(&self) {
+        if let Some(tick) = self.tick.take() {
+            tick.remove();
+        }
+
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+    }
+
+    fn stop(&self) {
+        self.stop_timers();
+        self.resync.take();
+    }
+
+    /// Wake up right after the next minute boundary, like the digital clock
+    fn schedule(&self) {
+        let elapsed = glib::DateTime::now_local().map_or(0.0, |now| now.seconds() % 60.0);
+        let delay = Duration::from_secs_f64(60.0 - elapsed) + Duration::from_millis(10);
+
+        *self.source.borrow_mut() = Some(glib::timeout_add_local_once(
+            delay,
+            glib::clone!(
+                #[weak(rename_to = clock)]
+                self,
+                move || {
+                    // Source is already gone, it must not be removed
+                    clock.source.take();
+                    clock.obj().queue_draw();
+                    clock.schedule();
+                }
+            ),
+        ));
+    }
+}
+
+/// Round capped line along `turn` of a full circle clockwise from 12 o'clock,
+/// from `start` to `end` distance from `center`
+fn line(
+    snapshot: &gtk::Snapshot,
+    center: &graphene::Point,
+    turn: f32,
+    start: f32,
+    end: f32,
+    width: f32,
+    color: &gdk::RGBA,
+) {
+    let (sin, cos) = (turn * 2.0 * PI).sin_cos();
+
+    let path = gsk::PathBuilder::new();
+    path.move_to(center.x() + start * sin, center.y() - start * cos);
+    path.line_to(center.x() + end * sin, center.y() - end * cos);
+
+    let stroke = gsk::Stroke::new(width);
+    stroke.set_line_cap(gsk::LineCap::Round);
+
+    snapshot.append_stroke(&path.to_path(), &stroke, color);
+}
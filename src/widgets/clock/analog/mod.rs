@@ -0,0 +1,37 @@
+mod imp;
+
+use gtk::{Accessible, Buildable, ConstraintTarget, Widget, glib};
+
+use crate::css;
+
+glib::wrapper! {
+    pub struct AnalogClock(ObjectSubclass<imp::AnalogClock>)
+        @extends Widget,
+        @implements Accessible, Buildable, ConstraintTarget;
+}
+
+impl AnalogClock {
+    /// Colors come from CSS nodes `hand.hour`, `hand.minute`, `hand.second`,
+    /// `tick.hour` and `tick.minute` inside `analog-clock`
+    pub fn new(seconds: bool) -> Self {
+        css::attach_style(
+            "
+analog-clock {
+    min-width: 200px;
+    min-height: 200px;
+    border-radius: 100%;
+    background-color: alpha(black, 0.3);
+    color: white;
+}
+analog-clock tick.minute {
+    color: alpha(currentColor, 0.5);
+}
+analog-clock hand.second {
+    color: #e01b24;
+}
+",
+        );
+
+        glib::Object::builder().property("seconds", seconds).build()
+    }
+}
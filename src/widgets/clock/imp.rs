@@ -1,12 +1,47 @@
-use std::cell::RefCell;
+use std::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
 
-use gtk::{Align, Box, Label, Orientation, glib, prelude::*, subclass::prelude::*};
+use gtk::{
+    Align,
+    Box,
+    Calendar,
+    GestureClick,
+    Label,
+    Orientation,
+    Popover,
+    glib,
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use super::{ZoneClocks, resync::Resync, time_zone};
+use crate::log;
+
+/// Labels of a time zone clock
+#[derive(Debug)]
+pub struct Zone {
+    pub zone: glib::TimeZone,
+    pub time_format: String,
+    pub date_format: Option<String>,
+    pub time_label: Label,
+    pub date_label: Label,
+}
 
 #[derive(Debug, Default, glib::Properties)]
 #[properties(wrapper_type = super::Clock)]
 pub struct Clock {
     pub time_label: RefCell<Label>,
     pub date_label: RefCell<Label>,
+    /// Month calendar shown by clicking the date
+    pub calendar: RefCell<Option<Popover>>,
+    pub zone_labels: RefCell<Vec<Zone>>,
+    /// Some format shows seconds, otherwise we update once a minute
+    pub seconds: Cell<bool>,
+    pub source: RefCell<Option<glib::SourceId>>,
+    /// Exists while mapped
+    pub resync: RefCell<Option<Resync>>,
 
     /// Time format
     #[property(get, set, construct)]
@@ -15,6 +50,10 @@ pub struct Clock {
     /// Date format
     #[property(get, set, construct)]
     pub date_format: RefCell<String>,
+
+    /// Additional clocks for other time zones
+    #[property(get, set, construct)]
+    pub zones: RefCell<ZoneClocks>,
 }
 
 #[glib::object_subclass]
@@ -38,56 +77,243 @@ impl ObjectImpl for Clock {
         let time_label = Label::builder().css_classes(["time"]).build();
         let date_label = Label::builder().css_classes(["date"]).build();
 
-        update_time(
-            &self.time_format.borrow(),
-            &self.date_format.borrow(),
-            &time_label,
-            &date_label,
-        );
-
         obj.append(&time_label);
         obj.append(&date_label);
 
-        glib::timeout_add_local(
-            std::time::Duration::from_secs(1),
-            glib::clone!(
-                #[strong(rename_to = clock)]
-                self.obj(),
-                move || {
-                    update_time(
-                        &clock.imp().time_format.borrow(),
-                        &clock.imp().date_format.borrow(),
-                        &clock.imp().time_label.borrow(),
-                        &clock.imp().date_label.borrow(),
-                    );
-                    glib::ControlFlow::Continue
+        let calendar = Calendar::new();
+        let popover = Popover::builder().child(&calendar).build();
+        popover.set_parent(&date_label);
+
+        let click = GestureClick::new();
+        click.connect_released(glib::clone!(
+            #[weak]
+            calendar,
+            #[weak]
+            popover,
+            move |_, _, _, _| {
+                // Calendar may have been browsed to another month last time
+                if let Ok(now) = glib::DateTime::now_local() {
+                    calendar.select_day(&now);
                 }
-            ),
-        );
+                popover.popup();
+            }
+        ));
+        date_label.add_controller(click);
+        *self.calendar.borrow_mut() = Some(popover);
 
         *self.time_label.borrow_mut() = time_label;
         *self.date_label.borrow_mut() = date_label;
+
+        self.build_zones();
+        self.update();
+
+        let zones = self.zone_labels.borrow();
+        self.seconds.set(
+            has_seconds(&self.time_format.borrow())
+                || has_seconds(&self.date_format.borrow())
+                || zones.iter().any(|zone| {
+                    has_seconds(&zone.time_format)
+                        || zone.date_format.as_deref().is_some_and(has_seconds)
+                }),
+        );
+    }
+
+    fn dispose(&self) {
+        self.stop();
+
+        if let Some(popover) = self.calendar.take() {
+            popover.unparent();
+        }
     }
 }
 
-impl WidgetImpl for Clock {}
+impl WidgetImpl for Clock {
+    // Unmapped clock does not tick, so laptops wake up less
+    fn map(&self) {
+        self.parent_map();
+
+        self.restart();
+        *self.resync.borrow_mut() = Some(Resync::new(glib::clone!(
+            #[weak(rename_to = clock)]
+            self,
+            move || clock.restart()
+        )));
+    }
+
+    fn unmap(&self) {
+        self.stop();
+        self.parent_unmap();
+    }
+}
 impl BoxImpl for Clock {}
 
+impl Clock {
+    /// Row of small clocks under the main one
+    fn build_zones(&self) {
+        let row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(24)
+            .halign(Align::Center)
+            .css_classes(["zones"])
+            .build();
+
+        let zones = self
+            .zones
+            .borrow()
+            .0
+            .iter()
+            .filter_map(|zone| {
+                let Some(time_zone) = time_zone(&zone.zone) else {
+                    log::warning!("unknown time zone {:?}", zone.zone);
+                    return None;
+                };
+
+                let column = Box::new(Orientation::Vertical, 2);
+                let time_label = Label::builder().css_classes(["zone-time"]).build();
+                let date_label = Label::builder()
+                    .css_classes(["zone-date"])
+                    .visible(zone.date_format.is_some())
+                    .build();
+
+                column.append(
+                    &Label::builder()
+                        .label(zone.label())
+                        .css_classes(["zone-name"])
+                        .build(),
+                );
+                column.append(&time_label);
+                column.append(&date_label);
+                row.append(&column);
+
+                Some(Zone {
+                    zone: time_zone,
+                    time_format: zone
+                        .time_format
+                        .clone()
+                        .unwrap_or_else(|| self.time_format.borrow().clone()),
+                    date_format: zone.date_format.clone(),
+                    time_label,
+                    date_label,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if !zones.is_empty() {
+            self.obj().append(&row);
+        }
+
+        *self.zone_labels.borrow_mut() = zones;
+    }
+
+    /// Update now and wait for the next change
+    fn restart(&self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+
+        self.update();
+        self.schedule();
+    }
+
+    fn stop(&self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+
+        self.resync.take();
+    }
+
+    /// Wake up right after the next second or minute boundary
+    fn schedule(&self) {
+        let period = if self.seconds.get() { 1.0 } else { 60.0 };
+        let elapsed = glib::DateTime::now_local().map_or(0.0, |now| now.seconds() % period);
+        // Firing a bit late is fine, early would show the old time for a whole period
+        let delay = Duration::from_secs_f64(period - elapsed) + Duration::from_millis(10);
+
+        *self.source.borrow_mut() = Some(glib::timeout_add_local_once(
+            delay,
+            glib::clone!(
+                #[weak(rename_to = clock)]
+                self,
+                move || {
+                    // Source is already gone, it must not be removed
+                    clock.source.take();
+                    clock.update();
+                    clock.schedule();
+                }
+            ),
+        ));
+    }
+
+    fn update(&self) {
+        let time = self.time_label.borrow();
+        let date = self.date_label.borrow();
+
+        match glib::DateTime::now_local() {
+            Ok(now) => {
+                time.set_text(&format(&now, &self.time_format.borrow()));
+                date.set_text(&format(&now, &self.date_format.borrow()));
+            }
+            Err(err) => {
+                time.set_text(&err.message);
+                date.set_text("");
+            }
+        }
+
+        for zone in self.zone_labels.borrow().iter() {
+            match glib::DateTime::now(&zone.zone) {
+                Ok(now) => {
+                    zone.time_label.set_text(&format(&now, &zone.time_format));
+                    if let Some(date_format) = &zone.date_format {
+                        zone.date_label.set_text(&format(&now, date_format));
+                    }
+                }
+                Err(err) => zone.time_label.set_text(&err.message),
+            }
+        }
+    }
+}
+
+/// Whether `format` has a conversion changing every second
+fn has_seconds(format: &str) -> bool {
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+
+        // Skip padding and alternative digits modifiers
+        let conversion = chars.find(|c| !matches!(c, '-' | '_' | '0' | 'O' | 'E'));
+
+        if matches!(conversion, Some('S' | 's' | 'T' | 'r' | 'X' | 'c' | 'f')) {
+            return true;
+        }
+    }
+
+    false
+}
+
 fn format(now: &glib::DateTime, fmt: &str) -> glib::GString {
     now.format(fmt)
         .map_err(|err| err.message)
         .unwrap_or_else(std::convert::Into::into)
 }
 
-fn update_time(time_format: &str, date_format: &str, time: &Label, date: &Label) {
-    match glib::DateTime::now_local() {
-        Ok(now) => {
-            time.set_text(&format(&now, time_format));
-            date.set_text(&format(&now, date_format));
-        }
-        Err(err) => {
-            time.set_text(&err.message);
-            date.set_text("");
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seconds_in_format() {
+        assert!(has_seconds("%H:%M:%S"));
+        assert!(has_seconds("%T"));
+        assert!(has_seconds("%r"));
+        assert!(has_seconds("%-S"));
+
+        assert!(!has_seconds("%H:%M"));
+        // Escaped percent sign followed by a plain S
+        assert!(!has_seconds("%%S"));
+        assert!(!has_seconds("Seconds"));
     }
 }
@@ -1,6 +1,12 @@
+mod analog;
 mod imp;
+mod resync;
 
+pub use analog::AnalogClock;
+use clap::ValueEnum;
 use gtk::{Accessible, Box, Buildable, ConstraintTarget, Orientable, Widget, glib};
+pub use resync::Resync;
+use serde::{Deserialize, Serialize};
 
 glib::wrapper! {
     pub struct Clock(ObjectSubclass<imp::Clock>)
@@ -8,11 +14,78 @@ glib::wrapper! {
         @implements Accessible, Buildable, ConstraintTarget, Orientable;
 }
 
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum, glib::Enum,
+)]
+#[serde(rename_all = "lowercase")]
+#[enum_type(name = "WaylockClockStyle")]
+pub enum ClockStyle {
+    #[default]
+    Digital,
+    Analog,
+    /// Analog clock above digital one
+    Both,
+}
+
+impl ClockStyle {
+    pub const fn has_digital(self) -> bool {
+        matches!(self, Self::Digital | Self::Both)
+    }
+
+    pub const fn has_analog(self) -> bool {
+        matches!(self, Self::Analog | Self::Both)
+    }
+}
+
+/// Additional clock for an IANA time zone
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZoneClock {
+    /// IANA identifier like `Europe/Berlin`
+    pub zone: String,
+    /// Shown above the time, last part of `zone` by default
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Main clock time format by default
+    #[serde(default)]
+    pub time_format: Option<String>,
+    /// Date is not shown by default
+    #[serde(default)]
+    pub date_format: Option<String>,
+}
+
+impl ZoneClock {
+    pub fn label(&self) -> String {
+        self.label.clone().unwrap_or_else(|| {
+            self.zone
+                .rsplit('/')
+                .next()
+                .unwrap_or(&self.zone)
+                .replace('_', " ")
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, glib::Boxed)]
+#[boxed_type(name = "WaylockZoneClocks")]
+pub struct ZoneClocks(pub Vec<ZoneClock>);
+
+/// `TimeZone::from_identifier` needs GLib 2.68 bindings, older `new` falls back to UTC
+#[allow(deprecated)]
+pub fn time_zone(identifier: &str) -> Option<glib::TimeZone> {
+    let zone = glib::TimeZone::new(Some(identifier));
+    (zone.identifier() == identifier).then_some(zone)
+}
+
 impl Clock {
-    pub fn new(time_format: impl AsRef<str>, date_format: impl AsRef<str>) -> Self {
+    pub fn new(
+        time_format: impl AsRef<str>,
+        date_format: impl AsRef<str>,
+        zones: ZoneClocks,
+    ) -> Self {
         glib::Object::builder()
             .property("time-format", time_format.as_ref())
             .property("date-format", date_format.as_ref())
+            .property("zones", zones)
             .build()
     }
 }
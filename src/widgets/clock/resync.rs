@@ -0,0 +1,123 @@
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    rc::Rc,
+};
+
+use gtk::{gio, glib, prelude::*};
+
+use crate::{log, logind};
+
+/// Calls back when wall clock time jumps: the clock is set or the system resumes from sleep.
+/// Timers run on the monotonic clock, which stops during suspend, so they fire late after resume.
+#[derive(Debug)]
+pub struct Resync {
+    clock_set: Option<glib::SourceId>,
+    resume: Option<(gio::DBusConnection, gio::SignalSubscriptionId)>,
+}
+
+impl Resync {
+    pub fn new(callback: impl Fn() + 'static) -> Self {
+        let callback = Rc::new(callback);
+
+        Self {
+            clock_set: clock_set(callback.clone())
+                .map_err(|err| log::warning!("failed to watch for clock changes: {err}"))
+                .ok(),
+            resume: resume(callback),
+        }
+    }
+}
+
+impl Drop for Resync {
+    fn drop(&mut self) {
+        if let Some(source) = self.clock_set.take() {
+            source.remove();
+        }
+
+        if let Some((connection, id)) = self.resume.take() {
+            connection.signal_unsubscribe(id);
+        }
+    }
+}
+
+/// Far future realtime timer, reading it fails with `ECANCELED` once the clock is set
+fn arm(fd: &OwnedFd) -> io::Result<()> {
+    let spec = libc::itimerspec {
+        it_interval: libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        },
+        it_value: libc::timespec {
+            tv_sec: libc::time_t::MAX,
+            tv_nsec: 0,
+        },
+    };
+
+    let result = unsafe {
+        libc::timerfd_settime(
+            fd.as_raw_fd(),
+            libc::TFD_TIMER_ABSTIME | libc::TFD_TIMER_CANCEL_ON_SET,
+            &spec,
+            std::ptr::null_mut(),
+        )
+    };
+
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn clock_set(callback: Rc<dyn Fn()>) -> io::Result<glib::SourceId> {
+    let fd = unsafe {
+        libc::timerfd_create(libc::CLOCK_REALTIME, libc::TFD_CLOEXEC | libc::TFD_NONBLOCK)
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    arm(&fd)?;
+
+    // Source owns the descriptor, it is closed when the source is removed
+    Ok(glib::unix_fd_add_local(
+        fd.as_raw_fd(),
+        glib::IOCondition::IN,
+        move |_, _| {
+            let mut expirations = [0u8; 8];
+            unsafe { libc::read(fd.as_raw_fd(), expirations.as_mut_ptr().cast(), 8) };
+
+            if let Err(err) = arm(&fd) {
+                log::warning!("failed to rearm clock change timer: {err}");
+            }
+
+            callback();
+            glib::ControlFlow::Continue
+        },
+    ))
+}
+
+/// logind `PrepareForSleep(false)` is sent after resume
+fn resume(callback: Rc<dyn Fn()>) -> Option<(gio::DBusConnection, gio::SignalSubscriptionId)> {
+    let connection = gio::bus_get_sync(gio::BusType::System, gio::Cancellable::NONE)
+        .map_err(|err| log::info!("no system bus, clock may lag after resume: {err}"))
+        .ok()?;
+
+    let id = connection.signal_subscribe(
+        Some(logind::DESTINATION),
+        Some(logind::MANAGER),
+        Some("PrepareForSleep"),
+        Some(logind::PATH),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |_, _, _, _, _, parameters| {
+            if parameters.get::<(bool,)>() == Some((false,)) {
+                callback();
+            }
+        },
+    );
+
+    Some((connection, id))
+}
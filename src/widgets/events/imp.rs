@@ -0,0 +1,137 @@
+use std::{cell::RefCell, time::Duration};
+
+use gtk::{Align, Box, Label, Orientation, gio, glib, pango, prelude::*, subclass::prelude::*};
+
+use super::Calendars;
+use crate::{
+    calendar::{self, Event},
+    log,
+};
+
+/// Calendars may be synced while locked
+const REFRESH: Duration = Duration::from_secs(300);
+const MAX_EVENTS: usize = 5;
+
+#[derive(Debug, Default, glib::Properties)]
+#[properties(wrapper_type = super::Events)]
+pub struct Events {
+    #[property(get, set, construct)]
+    pub calendars: RefCell<Calendars>,
+
+    pub source: RefCell<Option<glib::SourceId>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Events {
+    const NAME: &str = "Events";
+    type Type = super::Events;
+    type ParentType = Box;
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for Events {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+
+        obj.set_orientation(Orientation::Vertical);
+        obj.set_spacing(4);
+        obj.set_halign(Align::Center);
+        obj.add_css_class("events");
+        obj.set_visible(false);
+
+        if self.calendars.borrow().0.is_empty() {
+            return;
+        }
+
+        self.load();
+
+        *self.source.borrow_mut() = Some(glib::timeout_add_local(
+            REFRESH,
+            glib::clone!(
+                #[weak(rename_to = events)]
+                self,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    events.load();
+                    glib::ControlFlow::Continue
+                }
+            ),
+        ));
+    }
+
+    fn dispose(&self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+    }
+}
+
+impl WidgetImpl for Events {}
+
+impl BoxImpl for Events {}
+
+impl Events {
+    fn load(&self) {
+        let calendars = self.calendars.borrow().0.clone();
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = events)]
+            self,
+            async move {
+                match gio::spawn_blocking(move || calendar::today(&calendars)).await {
+                    Ok(today) => events.show(&today),
+                    Err(_) => log::warning!("failed to read calendars"),
+                }
+            }
+        ));
+    }
+
+    fn show(&self, today: &[Event]) {
+        let obj = self.obj();
+
+        while let Some(child) = obj.first_child() {
+            obj.remove(&child);
+        }
+
+        let Ok(now) = glib::DateTime::now_local() else {
+            return;
+        };
+
+        let upcoming = today
+            .iter()
+            .filter(|event| event.end > now)
+            .take(MAX_EVENTS)
+            .collect::<Vec<_>>();
+
+        for event in &upcoming {
+            let time = if event.all_day {
+                "All day".into()
+            } else {
+                event.start.format("%H:%M").unwrap_or_default()
+            };
+
+            let row = Box::new(Orientation::Horizontal, 12);
+            row.append(
+                &Label::builder()
+                    .label(time)
+                    .css_classes(["event-time"])
+                    .build(),
+            );
+            row.append(
+                &Label::builder()
+                    .label(&event.summary)
+                    .ellipsize(pango::EllipsizeMode::End)
+                    .max_width_chars(40)
+                    .xalign(0.0)
+                    .css_classes(["event-summary"])
+                    .build(),
+            );
+            obj.append(&row);
+        }
+
+        obj.set_visible(!upcoming.is_empty());
+    }
+}
@@ -0,0 +1,36 @@
+mod imp;
+
+use std::path::PathBuf;
+
+use gtk::{Accessible, Box, Buildable, ConstraintTarget, Orientable, Widget, glib};
+
+use crate::css;
+
+glib::wrapper! {
+    pub struct Events(ObjectSubclass<imp::Events>)
+        @extends Widget, Box,
+        @implements Accessible, Buildable, ConstraintTarget, Orientable;
+}
+
+/// `.ics` files or directories of them
+#[derive(Debug, Default, Clone, PartialEq, Eq, glib::Boxed)]
+#[boxed_type(name = "WaylockCalendars")]
+pub struct Calendars(pub Vec<PathBuf>);
+
+impl Events {
+    /// Today's upcoming events, hidden when there are none
+    pub fn new(calendars: Calendars) -> Self {
+        css::attach_style(
+            "
+.event-time {
+    font-family: monospace;
+    opacity: 0.7;
+}
+",
+        );
+
+        glib::Object::builder()
+            .property("calendars", calendars)
+            .build()
+    }
+}
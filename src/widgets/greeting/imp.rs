@@ -0,0 +1,215 @@
+use std::{cell::RefCell, time::Duration};
+
+use gtk::{Align, Box, Label, glib, pango, prelude::*, subclass::prelude::*};
+
+use super::{PartOfDay, Templates, render};
+use crate::{
+    log,
+    state::{self, LockState},
+    widgets::clock::Resync,
+};
+
+#[derive(Debug, Default, glib::Properties)]
+#[properties(wrapper_type = super::Greeting)]
+pub struct Greeting {
+    pub label: RefCell<Label>,
+    pub source: RefCell<Option<glib::SourceId>>,
+    /// Reschedules after the clock is set or the system resumes
+    pub resync: RefCell<Option<Resync>>,
+    /// Source of `{real_name}`, same as for the user info widget
+    #[cfg(feature = "userinfo")]
+    pub user: RefCell<Option<accounts_service::User>>,
+
+    #[property(get, set, construct)]
+    pub templates: RefCell<Templates>,
+
+    /// Source of `{locked_for}`, `{locked_since}` and `{failed_attempts}`
+    #[property(get, set, construct_only)]
+    pub state: RefCell<LockState>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Greeting {
+    const NAME: &str = "Greeting";
+    type Type = super::Greeting;
+    type ParentType = Box;
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for Greeting {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_halign(Align::Center);
+
+        if self.templates.borrow().is_empty() {
+            obj.set_visible(false);
+            return;
+        }
+
+        let label = Label::builder()
+            .css_classes(["greeting"])
+            .justify(gtk::Justification::Center)
+            .wrap(true)
+            .build();
+        obj.append(&label);
+        *self.label.borrow_mut() = label;
+
+        let state = self.state.borrow();
+        for property in ["locked-at", "failed-attempts"] {
+            state.connect_notify_local(
+                Some(property),
+                glib::clone!(
+                    #[weak(rename_to = greeting)]
+                    self,
+                    move |_, _| greeting.update()
+                ),
+            );
+        }
+
+        #[cfg(feature = "userinfo")]
+        self.watch_user();
+
+        self.restart();
+        *self.resync.borrow_mut() = Some(Resync::new(glib::clone!(
+            #[weak(rename_to = greeting)]
+            self,
+            move || greeting.restart()
+        )));
+    }
+
+    fn dispose(&self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+
+        self.resync.take();
+    }
+}
+
+impl WidgetImpl for Greeting {}
+impl BoxImpl for Greeting {}
+
+impl Greeting {
+    /// Update now and wait for the next minute
+    fn restart(&self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+
+        self.update();
+        self.schedule();
+    }
+
+    /// `{locked_for}` is in minutes, part of the day changes on the hour,
+    /// both together with the clock
+    fn schedule(&self) {
+        let elapsed = glib::DateTime::now_local().map_or(0.0, |now| now.seconds());
+        let delay = Duration::from_secs_f64(60.0 - elapsed) + Duration::from_millis(10);
+
+        *self.source.borrow_mut() = Some(glib::timeout_add_local_once(
+            delay,
+            glib::clone!(
+                #[weak(rename_to = greeting)]
+                self,
+                move || {
+                    // Source is already gone, it must not be removed
+                    greeting.source.take();
+                    greeting.update();
+                    greeting.schedule();
+                }
+            ),
+        ));
+    }
+
+    /// Update once AccountsService has loaded the user and whenever it changes
+    #[cfg(feature = "userinfo")]
+    fn watch_user(&self) {
+        use accounts_service::{UserManager, UserManagerExt};
+
+        let Some(user) = UserManager::default()
+            .and_then(|manager| manager.user(&glib::user_name().to_string_lossy()))
+        else {
+            return;
+        };
+
+        user.connect_is_loaded_notify(glib::clone!(
+            #[weak(rename_to = greeting)]
+            self,
+            move |_| greeting.update()
+        ));
+        user.connect_changed(glib::clone!(
+            #[weak(rename_to = greeting)]
+            self,
+            move |_| greeting.update()
+        ));
+
+        *self.user.borrow_mut() = Some(user);
+    }
+
+    /// Real name from AccountsService, `None` until it is loaded or without `userinfo`
+    fn real_name(&self) -> Option<String> {
+        #[cfg(feature = "userinfo")]
+        if let Some(user) = self.user.borrow().as_ref()
+            && user.is_loaded()
+        {
+            return user
+                .real_name()
+                .map(String::from)
+                .filter(|name| !name.is_empty());
+        }
+
+        None
+    }
+
+    fn variables(&self, part: PartOfDay) -> Vec<(&'static str, String)> {
+        let state = self.state.borrow();
+        let user = glib::user_name().to_string_lossy().into_owned();
+        let real_name = self.real_name().unwrap_or_else(|| user.clone());
+
+        vec![
+            ("user", user),
+            ("real_name", real_name),
+            ("hostname", glib::host_name().into()),
+            ("greeting", part.greeting().into()),
+            (
+                "locked_for",
+                state
+                    .locked_minutes()
+                    .map(state::format_minutes)
+                    .unwrap_or_default(),
+            ),
+            (
+                "locked_since",
+                state.locked_since().map(Into::into).unwrap_or_default(),
+            ),
+            ("failed_attempts", state.failed_attempts().to_string()),
+        ]
+    }
+
+    fn update(&self) {
+        let Ok(now) = glib::DateTime::now_local() else {
+            return;
+        };
+
+        let part = PartOfDay::from_hour(now.hour());
+        let templates = self.templates.borrow();
+        let Some(template) = templates.get(part) else {
+            self.obj().set_visible(false);
+            return;
+        };
+
+        let text = render(template, &self.variables(part));
+        let label = self.label.borrow();
+
+        if let Err(err) = pango::parse_markup(&text, '\0') {
+            log::warning!("invalid markup in greeting: {err}");
+            label.set_text(&text);
+        } else {
+            label.set_markup(&text);
+        }
+
+        self.obj().set_visible(true);
+    }
+}
@@ -0,0 +1,164 @@
+mod imp;
+
+use gtk::{Accessible, Box, Buildable, ConstraintTarget, Orientable, Widget, glib};
+use serde::{Deserialize, Serialize};
+
+use crate::{css, state::LockState};
+
+glib::wrapper! {
+    pub struct Greeting(ObjectSubclass<imp::Greeting>)
+        @extends Widget, Box,
+        @implements Accessible, Buildable, ConstraintTarget, Orientable;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartOfDay {
+    Morning,
+    Afternoon,
+    Evening,
+    Night,
+}
+
+impl PartOfDay {
+    pub const fn from_hour(hour: i32) -> Self {
+        match hour {
+            5..12 => Self::Morning,
+            12..17 => Self::Afternoon,
+            17..22 => Self::Evening,
+            _ => Self::Night,
+        }
+    }
+
+    /// Value of `{greeting}`
+    pub const fn greeting(self) -> &'static str {
+        match self {
+            Self::Morning => "Good morning",
+            Self::Afternoon => "Good afternoon",
+            Self::Evening | Self::Night => "Good evening",
+        }
+    }
+}
+
+/// Templates used instead of `greeting` at parts of the day
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GreetingVariants {
+    /// 05:00 to 12:00
+    #[serde(default)]
+    pub morning: Option<String>,
+    /// 12:00 to 17:00
+    #[serde(default)]
+    pub afternoon: Option<String>,
+    /// 17:00 to 22:00
+    #[serde(default)]
+    pub evening: Option<String>,
+    /// 22:00 to 05:00
+    #[serde(default)]
+    pub night: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, glib::Boxed)]
+#[boxed_type(name = "WaylockGreetingTemplates")]
+pub struct Templates {
+    pub default: Option<String>,
+    pub variants: GreetingVariants,
+}
+
+impl Templates {
+    pub fn get(&self, part: PartOfDay) -> Option<&str> {
+        let variant = match part {
+            PartOfDay::Morning => &self.variants.morning,
+            PartOfDay::Afternoon => &self.variants.afternoon,
+            PartOfDay::Evening => &self.variants.evening,
+            PartOfDay::Night => &self.variants.night,
+        };
+
+        variant.as_deref().or(self.default.as_deref())
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.default.is_none()
+            && self.variants.morning.is_none()
+            && self.variants.afternoon.is_none()
+            && self.variants.evening.is_none()
+            && self.variants.night.is_none()
+    }
+}
+
+/// Replace `{name}` in Pango markup `template`, values are escaped.
+/// Single pass, so braces inside values are never replaced in turn.
+pub fn render(template: &str, variables: &[(&str, String)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        text.push_str(&rest[..open]);
+        rest = &rest[open + 1..];
+
+        let variable = rest.find('}').and_then(|close| {
+            let (_, value) = variables.iter().find(|(name, _)| *name == &rest[..close])?;
+            Some((close, value))
+        });
+
+        match variable {
+            Some((close, value)) => {
+                text.push_str(&glib::markup_escape_text(value));
+                rest = &rest[close + 1..];
+            }
+            // Unknown names stay as written
+            None => text.push('{'),
+        }
+    }
+
+    text.push_str(rest);
+    text
+}
+
+impl Greeting {
+    /// Hidden when there are no templates
+    pub fn new(templates: Templates, state: &LockState) -> Self {
+        css::attach_style(
+            "
+.greeting {
+    font-size: 18pt;
+}
+",
+        );
+
+        glib::Object::builder()
+            .property("templates", templates)
+            .property("state", state)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitution() {
+        let variables = [("user", "ann".into()), ("hostname", "box".into())];
+
+        assert_eq!(render("Hi {user}@{hostname}", &variables), "Hi ann@box");
+        assert_eq!(render("{user}{user}", &variables), "annann");
+        assert_eq!(render("<b>{user}</b>", &variables), "<b>ann</b>");
+        assert_eq!(render("no variables", &variables), "no variables");
+    }
+
+    #[test]
+    fn unknown_and_unclosed() {
+        let variables = [("user", "ann".into())];
+
+        assert_eq!(render("{other} {user}", &variables), "{other} ann");
+        assert_eq!(render("{{user}}", &variables), "{ann}");
+        assert_eq!(render("{user", &variables), "{user");
+        assert_eq!(render("}{", &variables), "}{");
+    }
+
+    #[test]
+    fn values_are_not_substituted() {
+        let variables = [("real_name", "{user} <x>".into()), ("user", "ann".into())];
+
+        assert_eq!(render("{real_name}", &variables), "{user} &lt;x&gt;");
+    }
+}
@@ -0,0 +1,101 @@
+use std::{cell::RefCell, time::Duration};
+
+use gtk::{Align, Box, Label, glib, prelude::*, subclass::prelude::*};
+
+use crate::{state::LockState, widgets::clock::Resync};
+
+#[derive(Debug, Default, glib::Properties)]
+#[properties(wrapper_type = super::LockedSince)]
+pub struct LockedSince {
+    pub label: RefCell<Label>,
+    pub source: RefCell<Option<glib::SourceId>>,
+    /// Reschedules after the clock is set or the system resumes
+    pub resync: RefCell<Option<Resync>>,
+
+    #[property(get, set, construct_only)]
+    pub state: RefCell<LockState>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for LockedSince {
+    const NAME: &str = "LockedSince";
+    type Type = super::LockedSince;
+    type ParentType = Box;
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for LockedSince {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_halign(Align::Center);
+
+        let label = Label::builder().css_classes(["locked-since"]).build();
+        obj.append(&label);
+        *self.label.borrow_mut() = label;
+
+        self.state.borrow().connect_locked_at_notify(glib::clone!(
+            #[weak(rename_to = locked_since)]
+            self,
+            move |_| locked_since.update()
+        ));
+
+        self.restart();
+        *self.resync.borrow_mut() = Some(Resync::new(glib::clone!(
+            #[weak(rename_to = locked_since)]
+            self,
+            move || locked_since.restart()
+        )));
+    }
+
+    fn dispose(&self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+
+        self.resync.take();
+    }
+}
+
+impl WidgetImpl for LockedSince {}
+impl BoxImpl for LockedSince {}
+
+impl LockedSince {
+    /// Update now and wait for the next minute
+    fn restart(&self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+
+        self.update();
+        self.schedule();
+    }
+
+    /// Minutes change together with the clock
+    fn schedule(&self) {
+        let elapsed = glib::DateTime::now_local().map_or(0.0, |now| now.seconds());
+        let delay = Duration::from_secs_f64(60.0 - elapsed) + Duration::from_millis(10);
+
+        *self.source.borrow_mut() = Some(glib::timeout_add_local_once(
+            delay,
+            glib::clone!(
+                #[weak(rename_to = locked_since)]
+                self,
+                move || {
+                    // Source is already gone, it must not be removed
+                    locked_since.source.take();
+                    locked_since.update();
+                    locked_since.schedule();
+                }
+            ),
+        ));
+    }
+
+    fn update(&self) {
+        let text = self.state.borrow().describe();
+
+        self.obj().set_visible(text.is_some());
+        self.label.borrow().set_text(&text.unwrap_or_default());
+    }
+}
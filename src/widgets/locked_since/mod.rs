@@ -0,0 +1,18 @@
+mod imp;
+
+use gtk::{Accessible, Box, Buildable, ConstraintTarget, Orientable, Widget, glib};
+
+use crate::state::LockState;
+
+glib::wrapper! {
+    pub struct LockedSince(ObjectSubclass<imp::LockedSince>)
+        @extends Widget, Box,
+        @implements Accessible, Buildable, ConstraintTarget, Orientable;
+}
+
+impl LockedSince {
+    /// "Locked since 14:02 (35 min)", hidden until the session is locked
+    pub fn new(state: &LockState) -> Self {
+        glib::Object::builder().property("state", state).build()
+    }
+}
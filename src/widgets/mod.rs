@@ -7,6 +7,75 @@ pub mod playerctl;
 // #[cfg(feature = "screenshot")]
 // pub mod blur;
 
+pub mod battery;
 pub mod clock;
+pub mod events;
+pub mod greeting;
+pub mod locked_since;
+pub mod network;
 pub mod powerbar;
+pub mod slideshow;
 pub mod window;
+
+use clap::ValueEnum;
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+
+/// Optional parts of the lock screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum WidgetKind {
+    /// Text from `greeting` template
+    Greeting,
+    Clock,
+    /// Today's events from `calendars`
+    Events,
+    /// How long the session has been locked
+    LockedSince,
+    Userinfo,
+    Powerbar,
+    KeyboardLayout,
+    /// Charge from UPower, hidden without batteries
+    Battery,
+    /// Connectivity from NetworkManager
+    Network,
+    CapsLock,
+    NumLock,
+    SubmitButton,
+    Player,
+    Messages,
+}
+
+impl WidgetKind {
+    /// Whether the widget is compiled in
+    pub const fn is_available(self) -> bool {
+        match self {
+            Self::Userinfo => cfg!(feature = "userinfo"),
+            Self::Player => cfg!(feature = "playerctl"),
+            _ => true,
+        }
+    }
+}
+
+/// Widgets to show, in order within a common container
+#[derive(Debug, Clone, PartialEq, Eq, glib::Boxed)]
+#[boxed_type(name = "WaylockWidgets")]
+pub struct Widgets(pub Vec<WidgetKind>);
+
+impl Widgets {
+    pub fn contains(&self, kind: WidgetKind) -> bool {
+        self.0.contains(&kind)
+    }
+}
+
+impl Default for Widgets {
+    fn default() -> Self {
+        Self(
+            crate::config::default::WIDGETS
+                .iter()
+                .copied()
+                .filter(|kind| kind.is_available())
+                .collect(),
+        )
+    }
+}
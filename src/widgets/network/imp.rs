@@ -0,0 +1,205 @@
+use std::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
+
+use gtk::{
+    Align,
+    Box,
+    Frame,
+    Image,
+    Label,
+    Orientation,
+    gio,
+    glib,
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use crate::{
+    log,
+    network_manager::{self, Status},
+};
+
+/// Changes come in bursts, e.g. while a connection is being activated
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Default, glib::Properties)]
+#[properties(wrapper_type = super::Network)]
+pub struct Network {
+    /// NetworkManager reports no connection, unknown is not offline
+    #[property(get)]
+    pub offline: Cell<bool>,
+
+    pub icon: RefCell<Image>,
+    pub label: RefCell<Label>,
+    pub subscription: RefCell<Option<(gio::DBusConnection, gio::SignalSubscriptionId)>>,
+    /// Notices NetworkManager starting or stopping
+    pub watch: RefCell<Option<network_manager::Watch>>,
+    /// Object paths of the last status
+    pub watched: RefCell<Vec<String>>,
+    pub source: RefCell<Option<glib::SourceId>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Network {
+    const NAME: &str = "Network";
+    type Type = super::Network;
+    type ParentType = Box;
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for Network {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_visible(false);
+
+        let content = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .halign(Align::Center)
+            .spacing(4)
+            .margin_top(4)
+            .margin_start(4)
+            .margin_end(4)
+            .margin_bottom(4)
+            .build();
+
+        let icon = Image::new();
+        let label = Label::new(None);
+        content.append(&icon);
+        content.append(&label);
+
+        obj.append(
+            &Frame::builder()
+                .child(&content)
+                .css_classes(["bubble-frame", "network"])
+                .build(),
+        );
+
+        *self.icon.borrow_mut() = icon;
+        *self.label.borrow_mut() = label;
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = network)]
+            self,
+            async move {
+                let connection = match gio::bus_get_future(gio::BusType::System).await {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        log::info!("no system bus, network status is unknown: {err}");
+                        return;
+                    }
+                };
+
+                let id = connection.signal_subscribe(
+                    Some(network_manager::DESTINATION),
+                    Some(network_manager::PROPERTIES),
+                    Some("PropertiesChanged"),
+                    None,
+                    None,
+                    gio::DBusSignalFlags::NONE,
+                    glib::clone!(
+                        #[weak]
+                        network,
+                        move |_, _, path, _, _, _| {
+                            // Signal strength of every access point around changes all the time
+                            if network
+                                .watched
+                                .borrow()
+                                .iter()
+                                .any(|watched| watched == path)
+                            {
+                                network.schedule();
+                            }
+                        }
+                    ),
+                );
+
+                // Also does the first refresh
+                let watch = network_manager::Watch::new(
+                    &connection,
+                    glib::clone!(
+                        #[weak]
+                        network,
+                        move || network.schedule()
+                    ),
+                );
+
+                *network.subscription.borrow_mut() = Some((connection, id));
+                *network.watch.borrow_mut() = Some(watch);
+            }
+        ));
+    }
+
+    fn dispose(&self) {
+        if let Some((connection, id)) = self.subscription.take() {
+            connection.signal_unsubscribe(id);
+        }
+
+        self.watch.take();
+
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+    }
+}
+
+impl WidgetImpl for Network {}
+impl BoxImpl for Network {}
+
+impl Network {
+    fn schedule(&self) {
+        if self.source.borrow().is_some() {
+            return;
+        }
+
+        *self.source.borrow_mut() = Some(glib::timeout_add_local_once(
+            DEBOUNCE,
+            glib::clone!(
+                #[weak(rename_to = network)]
+                self,
+                move || {
+                    // Source is already gone, it must not be removed
+                    network.source.take();
+                    network.refresh();
+                }
+            ),
+        ));
+    }
+
+    fn refresh(&self) {
+        let Some(connection) = self
+            .subscription
+            .borrow()
+            .as_ref()
+            .map(|(connection, _)| connection.clone())
+        else {
+            return;
+        };
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = network)]
+            self,
+            async move {
+                let status = network_manager::status(&connection).await;
+                network.show(status);
+            }
+        ));
+    }
+
+    fn show(&self, status: Status) {
+        let obj = self.obj();
+
+        *self.watched.borrow_mut() = status.watched.clone();
+
+        if self.offline.replace(status.is_offline()) != status.is_offline() {
+            obj.notify_offline();
+        }
+
+        self.icon.borrow().set_icon_name(Some(status.icon()));
+        self.label.borrow().set_text(&status.describe());
+        obj.set_visible(status.link != network_manager::Link::Unknown);
+    }
+}
@@ -0,0 +1,22 @@
+mod imp;
+
+use gtk::{Accessible, Box, Buildable, ConstraintTarget, Orientable, Widget, glib};
+
+glib::wrapper! {
+    pub struct Network(ObjectSubclass<imp::Network>)
+        @extends Widget, Box,
+        @implements Accessible, Buildable, ConstraintTarget, Orientable;
+}
+
+impl Network {
+    /// Connectivity from NetworkManager, hidden when it is not running
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,62 +1,303 @@
-use std::{ffi::OsString, time::Duration};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
 
-use gtk::{Box, Button, Orientation, glib, prelude::*, subclass::prelude::*};
+use gtk::{
+    Box,
+    Button,
+    GestureClick,
+    GestureLongPress,
+    Label,
+    Orientation,
+    Popover,
+    PropagationPhase,
+    gio,
+    glib,
+    prelude::*,
+    subclass::prelude::*,
+};
 
-use crate::log;
+use super::{Confirm, PowerAction, PowerActions};
+use crate::{display_manager, log, logind};
 
-enum PowerOption {
-    Shutdown,
-    Reboot,
-    Suspend,
-    // UserSwitch,
-    // Logout,
+const BUTTON_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keep the button from being clicked again while the action is in progress
+fn disable_for_a_while(button: &Button) {
+    if BUTTON_TIMEOUT > Duration::ZERO {
+        button.set_sensitive(false);
+
+        glib::timeout_add_local_once(
+            BUTTON_TIMEOUT,
+            glib::clone!(
+                #[weak]
+                button,
+                move || button.set_sensitive(true)
+            ),
+        );
+    }
 }
 
-const BUTTON_TIMEOUT: Duration = Duration::from_secs(5);
+fn run_command(button: &Button, cmdline: &str) {
+    disable_for_a_while(button);
+
+    if let Err(err) = glib::spawn_command_line_async(cmdline) {
+        log::warning!("failed to run {cmdline:?}: {err}");
+    }
+}
 
-impl PowerOption {
-    const fn get_icon(&self) -> &str {
-        match self {
-            Self::Shutdown => "system-shutdown-symbolic",
-            Self::Reboot => "system-reboot-symbolic",
-            Self::Suspend => "weather-clear-night-symbolic",
-            // Self::UserSwitch => "system-switch-user-symbolic",
-            // Self::Logout => "system-log-out-symbolic",
+async fn perform(action: PowerAction) -> Result<(), glib::Error> {
+    match action {
+        PowerAction::SwitchUser => match display_manager::find().await {
+            Some(manager) => display_manager::switch_to_greeter(&manager).await,
+            None => Err(glib::Error::new(
+                gio::IOErrorEnum::NotSupported,
+                "no supported display manager",
+            )),
+        },
+        PowerAction::Logout => {
+            logind::call(action.method(), Some((logind::session_id(),).to_variant()))
+                .await
+                .map(drop)
         }
+        // Not interactive, polkit agent can not show up above the lock screen
+        _ => logind::call(action.method(), Some((false,).to_variant()))
+            .await
+            .map(drop),
     }
+}
 
-    fn get_default_command(&self) -> Option<OsString> {
-        return Some("ls".into());
-        match self {
-            Self::Shutdown => Some("systemctl -i poweroff".into()),
-            Self::Reboot => Some("systemctl reboot".into()),
-            Self::Suspend => Some("systemctl suspend".into()),
-            // Self::UserSwitch => None,
-            // Self::Logout => None,
+/// Whether the button of `action` should be shown
+async fn available(action: PowerAction) -> bool {
+    let Some(can_method) = action.can_method() else {
+        return match action {
+            PowerAction::SwitchUser => display_manager::find().await.is_some(),
+            _ => true,
+        };
+    };
+
+    // Calls are not interactive, `challenge` would end with access denied
+    match logind::can(can_method).await {
+        Ok(answer) => answer == "yes",
+        Err(err) => {
+            log::info!("{can_method} failed: {err}");
+            false
         }
     }
 }
 
-fn button_clicked(button: &Button, cmdline: &OsString) {
-    if BUTTON_TIMEOUT > Duration::ZERO {
-        button.set_sensitive(false);
+fn call_builtin(button: &Button, action: PowerAction) {
+    disable_for_a_while(button);
 
-        glib::timeout_add_local(BUTTON_TIMEOUT, {
-            let btn = button.clone();
-            move || {
-                btn.set_sensitive(true);
-                glib::ControlFlow::Break
-            }
-        });
+    glib::spawn_future_local(async move {
+        if let Err(err) = perform(action).await {
+            log::warning!("{} failed: {err}", action.method());
+        }
+    });
+}
+
+fn countdown_text(action: PowerAction, remaining: u32) -> String {
+    format!("{} in {remaining} s", action.label())
+}
+
+/// Text shown without a countdown, until the action is confirmed
+fn prompt_text(action: PowerAction, confirm: Confirm) -> String {
+    match confirm {
+        Confirm::Hold => format!("Keep holding to {}", action.label().to_lowercase()),
+        _ => format!("{}?", action.label()),
     }
+}
 
-    if let Err(err) = glib::spawn_command_line_async(cmdline) {
-        log::warning!("Failed to run app ${cmdline:?} {err}");
+/// Seconds left of a confirmation. Every start and stop begins a new generation,
+/// ticks scheduled by an earlier one are outdated and stop themselves.
+#[derive(Debug, Default)]
+struct Countdown {
+    generation: Cell<u64>,
+    remaining: Cell<u32>,
+}
+
+impl Countdown {
+    /// Start over from `seconds`, returns the generation its ticks belong to
+    fn start(&self, seconds: u32) -> u64 {
+        self.stop();
+        self.remaining.set(seconds);
+        self.generation.get()
+    }
+
+    fn stop(&self) {
+        self.generation.set(self.generation.get() + 1);
+    }
+
+    /// A second of `generation` has passed, seconds left or `None` if it is outdated
+    fn tick(&self, generation: u64) -> Option<u32> {
+        if generation != self.generation.get() {
+            return None;
+        }
+
+        let remaining = self.remaining.get().saturating_sub(1);
+        self.remaining.set(remaining);
+        Some(remaining)
     }
 }
 
-#[derive(Default)]
-pub struct PowerBar;
+/// Popover counting down to `run`, closing it cancels the countdown.
+/// Without countdown it waits for the "now" button, or a long press for `Confirm::Hold`.
+/// Returns the popover and a closure starting the countdown.
+fn confirmation(
+    button: &Button,
+    action: PowerAction,
+    confirm: Confirm,
+    seconds: u32,
+    builtin: bool,
+    run: Rc<dyn Fn(&Button)>,
+) -> (Popover, Rc<dyn Fn()>) {
+    let popover = Popover::builder()
+        .autohide(confirm == Confirm::Click)
+        .css_classes(["power-confirmation"])
+        .build();
+    popover.set_parent(button);
+
+    let content = Box::new(Orientation::Vertical, 8);
+    let status = Label::new(None);
+    let warning = Label::builder()
+        .wrap(true)
+        .max_width_chars(40)
+        .css_classes(["warning"])
+        .visible(false)
+        .build();
+    content.append(&status);
+    content.append(&warning);
+
+    if confirm == Confirm::Click {
+        let buttons = Box::new(Orientation::Horizontal, 8);
+        buttons.set_homogeneous(true);
+
+        let cancel = Button::with_label("Cancel");
+        cancel.connect_clicked(glib::clone!(
+            #[weak]
+            popover,
+            move |_| popover.popdown()
+        ));
+
+        let now = Button::with_label(&format!("{} now", action.label()));
+        now.add_css_class("destructive-action");
+        now.connect_clicked(glib::clone!(
+            #[weak]
+            popover,
+            #[weak]
+            button,
+            #[strong]
+            run,
+            move |_| {
+                popover.popdown();
+                run(&button);
+            }
+        ));
+
+        buttons.append(&cancel);
+        buttons.append(&now);
+        content.append(&buttons);
+    }
+
+    popover.set_child(Some(&content));
+
+    let countdown = Rc::new(Countdown::default());
+
+    popover.connect_closed(glib::clone!(
+        #[strong]
+        countdown,
+        move |_| countdown.stop()
+    ));
+
+    let start = Rc::new(glib::clone!(
+        #[weak]
+        popover,
+        #[weak]
+        button,
+        move || {
+            let current = countdown.start(seconds);
+
+            status.set_label(&if seconds == 0 {
+                prompt_text(action, confirm)
+            } else {
+                countdown_text(action, seconds)
+            });
+            warning.set_visible(false);
+            popover.popup();
+
+            if let Some(what) = action.inhibited().filter(|_| builtin) {
+                glib::spawn_future_local(glib::clone!(
+                    #[weak]
+                    warning,
+                    async move {
+                        match logind::blocking_inhibitors(what).await {
+                            Ok(inhibitors) if !inhibitors.is_empty() => {
+                                let list = inhibitors
+                                    .iter()
+                                    .map(|(who, why)| format!("{who} ({why})"))
+                                    .collect::<Vec<_>>()
+                                    .join(", ");
+
+                                warning.set_label(&format!("Blocked by: {list}"));
+                                warning.set_visible(true);
+                            }
+                            Ok(_) => {}
+                            Err(err) => log::info!("ListInhibitors failed: {err}"),
+                        }
+                    }
+                ));
+            }
+
+            if seconds == 0 {
+                return;
+            }
+
+            glib::timeout_add_local(
+                Duration::from_secs(1),
+                glib::clone!(
+                    #[weak]
+                    popover,
+                    #[weak]
+                    button,
+                    #[weak]
+                    status,
+                    #[strong]
+                    countdown,
+                    #[strong]
+                    run,
+                    #[upgrade_or]
+                    glib::ControlFlow::Break,
+                    move || match countdown.tick(current) {
+                        None => glib::ControlFlow::Break,
+                        Some(0) => {
+                            popover.popdown();
+                            run(&button);
+                            glib::ControlFlow::Break
+                        }
+                        Some(remaining) => {
+                            status.set_label(&countdown_text(action, remaining));
+                            glib::ControlFlow::Continue
+                        }
+                    }
+                ),
+            );
+        }
+    ));
+
+    (popover, start)
+}
+
+#[derive(Debug, Default, glib::Properties)]
+#[properties(wrapper_type = super::PowerBar)]
+pub struct PowerBar {
+    #[property(get, set, construct)]
+    pub actions: RefCell<PowerActions>,
+
+    /// Confirmation popovers, parented to the buttons
+    pub popovers: RefCell<Vec<Popover>>,
+}
 
 #[glib::object_subclass]
 impl ObjectSubclass for PowerBar {
@@ -65,6 +306,7 @@ impl ObjectSubclass for PowerBar {
     type ParentType = Box;
 }
 
+#[glib::derived_properties]
 impl ObjectImpl for PowerBar {
     fn constructed(&self) {
         let obj = self.obj();
@@ -73,26 +315,173 @@ impl ObjectImpl for PowerBar {
         obj.set_orientation(Orientation::Horizontal);
         obj.set_spacing(8);
 
-        for n in [
-            PowerOption::Shutdown,
-            PowerOption::Reboot,
-            PowerOption::Suspend,
-            // PowerOption::UserSwitch,
-            // PowerOption::Logout,
-        ] {
-            if let Some(cmdline) = n.get_default_command() {
-                let button = Button::new();
-                button.set_icon_name(n.get_icon());
-                button.connect_clicked(glib::clone!(
-                    #[weak]
-                    button,
-                    move |_| button_clicked(&button, &cmdline)
-                ));
-                obj.append(&button);
+        let actions = self.actions.borrow();
+
+        for &action in &actions.actions {
+            let config = actions.configs.get(&action).cloned().unwrap_or_default();
+
+            let button = Button::builder()
+                .icon_name(config.icon.as_deref().unwrap_or(action.icon()))
+                .tooltip_text(action.label())
+                .build();
+            obj.append(&button);
+
+            let run: Rc<dyn Fn(&Button)> = match config.command.clone() {
+                Some(cmdline) => Rc::new(move |button| run_command(button, &cmdline)),
+                None => Rc::new(move |button| call_builtin(button, action)),
+            };
+
+            match config.confirm(action) {
+                Confirm::None => {
+                    button.connect_clicked(move |button| run(button));
+                }
+                Confirm::Click => {
+                    let (popover, start) = confirmation(
+                        &button,
+                        action,
+                        Confirm::Click,
+                        config.countdown(),
+                        config.command.is_none(),
+                        run,
+                    );
+                    button.connect_clicked(move |_| start());
+                    self.popovers.borrow_mut().push(popover);
+                }
+                Confirm::Hold => {
+                    let seconds = config.countdown();
+                    let (popover, start) = confirmation(
+                        &button,
+                        action,
+                        Confirm::Hold,
+                        seconds,
+                        config.command.is_none(),
+                        run.clone(),
+                    );
+
+                    // Releasing the button before the countdown ends cancels it
+                    let gesture = GestureClick::new();
+                    gesture.set_propagation_phase(PropagationPhase::Capture);
+                    gesture.connect_pressed(move |_, _, _, _| start());
+                    gesture.connect_released(glib::clone!(
+                        #[weak]
+                        popover,
+                        move |_, _, _, _| popover.popdown()
+                    ));
+                    gesture.connect_stopped(glib::clone!(
+                        #[weak]
+                        popover,
+                        move |_| popover.popdown()
+                    ));
+                    button.add_controller(gesture);
+
+                    // Without countdown holding is complete once it is a long press
+                    if seconds == 0 {
+                        let long_press = GestureLongPress::new();
+                        long_press.set_propagation_phase(PropagationPhase::Capture);
+                        long_press.connect_pressed(glib::clone!(
+                            #[weak]
+                            popover,
+                            move |gesture, _, _| {
+                                popover.popdown();
+                                if let Some(button) = gesture.widget().and_downcast::<Button>() {
+                                    run(&button);
+                                }
+                            }
+                        ));
+                        button.add_controller(long_press);
+                    }
+
+                    self.popovers.borrow_mut().push(popover);
+                }
             }
+
+            if config.command.is_some() {
+                continue;
+            }
+
+            // Shown once the action turns out to be possible
+            button.set_visible(false);
+            glib::spawn_future_local(glib::clone!(
+                #[weak]
+                button,
+                async move { button.set_visible(available(action).await) }
+            ));
+        }
+    }
+
+    fn dispose(&self) {
+        for popover in self.popovers.take() {
+            popover.unparent();
         }
     }
 }
 
 impl WidgetImpl for PowerBar {}
 impl BoxImpl for PowerBar {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{logind::tests::Calls, test_bus};
+
+    #[test]
+    fn countdown_ticks_of_earlier_generations_stop() {
+        let countdown = Countdown::default();
+
+        let first = countdown.start(2);
+        assert_eq!(countdown.tick(first), Some(1));
+
+        // Popover closed
+        countdown.stop();
+        assert_eq!(countdown.tick(first), None);
+
+        // Started again before the old tick came
+        let second = countdown.start(3);
+        assert_eq!(countdown.tick(first), None);
+        assert_eq!(countdown.tick(second), Some(2));
+        assert_eq!(countdown.tick(second), Some(1));
+        assert_eq!(countdown.tick(second), Some(0));
+    }
+
+    #[test]
+    fn only_permitted_actions_are_shown() {
+        test_bus::run(async {
+            let _manager = logind::tests::manager(
+                &[
+                    ("CanPowerOff", "yes"),
+                    ("CanReboot", "challenge"),
+                    ("CanSuspend", "no"),
+                    ("CanHibernate", "na"),
+                ],
+                &Calls::default(),
+            );
+
+            assert!(available(PowerAction::PowerOff).await);
+            // Would need a polkit agent, which can not show above the lock screen
+            assert!(!available(PowerAction::Reboot).await);
+            assert!(!available(PowerAction::Suspend).await);
+            assert!(!available(PowerAction::Hibernate).await);
+            // logind failing to answer
+            assert!(!available(PowerAction::HybridSleep).await);
+
+            // No display manager on the test bus
+            assert!(!available(PowerAction::SwitchUser).await);
+            assert!(available(PowerAction::Logout).await);
+        });
+    }
+
+    #[test]
+    fn actions_are_not_interactive() {
+        test_bus::run(async {
+            let calls = Calls::default();
+            let _manager = logind::tests::manager(&[], &calls);
+
+            perform(PowerAction::Suspend).await.unwrap();
+
+            assert_eq!(
+                *calls.borrow(),
+                [(String::from("Suspend"), (false,).to_variant())]
+            );
+        });
+    }
+}
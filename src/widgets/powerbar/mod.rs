@@ -1,6 +1,12 @@
 mod imp;
 
+use std::collections::HashMap;
+
+use clap::ValueEnum;
 use gtk::{Accessible, Box, Buildable, ConstraintTarget, Orientable, Widget, glib};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
 
 glib::wrapper! {
     pub struct PowerBar(ObjectSubclass<imp::PowerBar>)
@@ -8,14 +14,143 @@ glib::wrapper! {
         @implements Accessible, Buildable, ConstraintTarget, Orientable;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PowerAction {
+    PowerOff,
+    Reboot,
+    Suspend,
+    Hibernate,
+    HybridSleep,
+    /// Show the greeter of the display manager, hidden without a supported one
+    SwitchUser,
+    /// Terminate the current session
+    Logout,
+}
+
+impl PowerAction {
+    /// D-Bus method doing the action
+    pub const fn method(self) -> &'static str {
+        match self {
+            Self::PowerOff => "PowerOff",
+            Self::Reboot => "Reboot",
+            Self::Suspend => "Suspend",
+            Self::Hibernate => "Hibernate",
+            Self::HybridSleep => "HybridSleep",
+            Self::SwitchUser => "SwitchToGreeter",
+            Self::Logout => "TerminateSession",
+        }
+    }
+
+    /// logind method telling whether the action is possible
+    pub const fn can_method(self) -> Option<&'static str> {
+        match self {
+            Self::PowerOff => Some("CanPowerOff"),
+            Self::Reboot => Some("CanReboot"),
+            Self::Suspend => Some("CanSuspend"),
+            Self::Hibernate => Some("CanHibernate"),
+            Self::HybridSleep => Some("CanHybridSleep"),
+            Self::SwitchUser | Self::Logout => None,
+        }
+    }
+
+    pub const fn icon(self) -> &'static str {
+        match self {
+            Self::PowerOff => "system-shutdown-symbolic",
+            Self::Reboot => "system-reboot-symbolic",
+            Self::Suspend => "weather-clear-night-symbolic",
+            Self::Hibernate => "drive-harddisk-symbolic",
+            Self::HybridSleep => "weather-few-clouds-night-symbolic",
+            Self::SwitchUser => "system-switch-user-symbolic",
+            Self::Logout => "system-log-out-symbolic",
+        }
+    }
+
+    /// What inhibitors may block the action
+    pub const fn inhibited(self) -> Option<&'static str> {
+        match self {
+            Self::PowerOff | Self::Reboot => Some("shutdown"),
+            Self::Suspend | Self::Hibernate | Self::HybridSleep => Some("sleep"),
+            Self::SwitchUser | Self::Logout => None,
+        }
+    }
+
+    /// Unsaved work behind the lock would be lost
+    pub const fn is_destructive(self) -> bool {
+        matches!(self, Self::PowerOff | Self::Reboot | Self::Logout)
+    }
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::PowerOff => "Power off",
+            Self::Reboot => "Reboot",
+            Self::Suspend => "Suspend",
+            Self::Hibernate => "Hibernate",
+            Self::HybridSleep => "Hybrid sleep",
+            Self::SwitchUser => "Switch user",
+            Self::Logout => "Log out",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confirm {
+    /// Run at once
+    None,
+    /// Click shows a popover counting down, closing it cancels
+    Click,
+    /// Button has to be held until the countdown ends, or for a long press without one
+    Hold,
+}
+
+/// Settings of an action
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowerActionConfig {
+    /// Shell command run instead of calling logind, the button is always shown
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// `click` for power off, reboot and logout, `none` for others
+    #[serde(default)]
+    pub confirm: Option<Confirm>,
+    /// Seconds before the action runs once confirmation starts,
+    /// with 0 a click waits for the "now" button
+    #[serde(default)]
+    pub countdown: Option<u32>,
+}
+
+impl PowerActionConfig {
+    pub fn confirm(&self, action: PowerAction) -> Confirm {
+        self.confirm.unwrap_or(if action.is_destructive() {
+            Confirm::Click
+        } else {
+            Confirm::None
+        })
+    }
+
+    pub fn countdown(&self) -> u32 {
+        self.countdown.unwrap_or(config::default::POWER_COUNTDOWN)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, glib::Boxed)]
+#[boxed_type(name = "WaylockPowerActions")]
+pub struct PowerActions {
+    /// Buttons in order
+    pub actions: Vec<PowerAction>,
+    pub configs: HashMap<PowerAction, PowerActionConfig>,
+}
+
 impl PowerBar {
-    pub fn new() -> Self {
-        glib::Object::new()
+    pub fn new(actions: PowerActions) -> Self {
+        glib::Object::builder().property("actions", actions).build()
     }
 }
 
 impl Default for PowerBar {
     fn default() -> Self {
-        Self::new()
+        Self::new(PowerActions::default())
     }
 }
@@ -0,0 +1,163 @@
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+    time::Duration,
+};
+
+use gtk::{
+    Box,
+    ContentFit,
+    Picture,
+    Stack,
+    StackTransitionType,
+    gdk,
+    gio,
+    glib,
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use super::{SlideshowOrder, SlideshowParams};
+use crate::log;
+
+#[derive(Debug, Default, glib::Properties)]
+#[properties(wrapper_type = super::Slideshow)]
+pub struct Slideshow {
+    pub stack: RefCell<Stack>,
+    /// Current image and the one we fade to
+    pub pictures: RefCell<[Picture; 2]>,
+    pub files: RefCell<Vec<PathBuf>>,
+    /// Next image in sequential order, the shown one in random order
+    pub index: Cell<usize>,
+    /// Random generator state
+    pub state: Cell<u32>,
+
+    #[property(get, set, construct)]
+    pub params: RefCell<SlideshowParams>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Slideshow {
+    const NAME: &str = "Slideshow";
+    type Type = super::Slideshow;
+    type ParentType = Box;
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for Slideshow {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_hexpand(true);
+        obj.set_vexpand(true);
+
+        let stack = Stack::builder()
+            .transition_type(StackTransitionType::Crossfade)
+            .transition_duration(self.params.borrow().transition)
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+
+        let pictures = [(); 2].map(|()| {
+            let picture = Picture::builder()
+                .css_name("background")
+                .content_fit(ContentFit::Cover)
+                .build();
+            stack.add_child(&picture);
+            picture
+        });
+
+        obj.append(&stack);
+
+        *self.stack.borrow_mut() = stack;
+        *self.pictures.borrow_mut() = pictures;
+    }
+}
+
+impl WidgetImpl for Slideshow {}
+impl BoxImpl for Slideshow {}
+
+impl Slideshow {
+    pub fn start(&self) {
+        self.next();
+
+        let interval = self.params.borrow().interval.max(1);
+
+        glib::timeout_add_local(
+            Duration::from_secs(interval),
+            glib::clone!(
+                #[weak(rename_to = slideshow)]
+                self.obj(),
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    slideshow.imp().next();
+                    glib::ControlFlow::Continue
+                }
+            ),
+        );
+    }
+
+    fn random(&self) -> u32 {
+        let mut x = self.state.get();
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state.set(x);
+        x
+    }
+
+    /// Decode next image on a worker thread and fade to it
+    fn next(&self) {
+        let path = {
+            let files = self.files.borrow();
+            if files.is_empty() {
+                return;
+            }
+
+            let index = match self.params.borrow().order {
+                SlideshowOrder::Sequential => self.index.replace(self.index.get() + 1),
+                SlideshowOrder::Random if files.len() > 1 => {
+                    // Pick among the others, so the same image is not shown twice in a row
+                    let current = self.index.get() % files.len();
+                    let index = self.random() as usize % (files.len() - 1);
+                    let index = if index >= current { index + 1 } else { index };
+
+                    self.index.set(index);
+                    index
+                }
+                SlideshowOrder::Random => 0,
+            };
+
+            files[index % files.len()].clone()
+        };
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = slideshow)]
+            self.obj(),
+            async move {
+                let file = path.clone();
+                match gio::spawn_blocking(move || gdk::Texture::from_filename(file)).await {
+                    Ok(Ok(texture)) => slideshow.imp().show(&texture),
+                    Ok(Err(err)) => log::warning!("failed to load {path:?}: {err}"),
+                    Err(_) => log::warning!("failed to load {path:?}"),
+                }
+            }
+        ));
+    }
+
+    fn show(&self, texture: &gdk::Texture) {
+        let stack = self.stack.borrow();
+        let pictures = self.pictures.borrow();
+
+        let hidden = if stack.visible_child().as_ref() == Some(pictures[0].upcast_ref()) {
+            &pictures[1]
+        } else {
+            &pictures[0]
+        };
+
+        hidden.set_paintable(Some(texture));
+        stack.set_visible_child(hidden);
+    }
+}
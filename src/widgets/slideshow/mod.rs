@@ -0,0 +1,141 @@
+mod imp;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use clap::ValueEnum;
+use gtk::{
+    Accessible,
+    Box,
+    Buildable,
+    ConstraintTarget,
+    Orientable,
+    Widget,
+    gio,
+    glib,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{config, log};
+
+glib::wrapper! {
+    pub struct Slideshow(ObjectSubclass<imp::Slideshow>)
+        @extends Widget, Box,
+        @implements Accessible, Buildable, ConstraintTarget, Orientable;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SlideshowOrder {
+    #[default]
+    Sequential,
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Boxed)]
+#[boxed_type(name = "WaylockSlideshowParams")]
+pub struct SlideshowParams {
+    /// Seconds between images
+    pub interval: u64,
+    /// Cross-fade duration in milliseconds
+    pub transition: u32,
+    pub order: SlideshowOrder,
+    /// Show different images on each monitor
+    pub per_monitor: bool,
+}
+
+impl Default for SlideshowParams {
+    fn default() -> Self {
+        Self {
+            interval: config::default::SLIDESHOW_INTERVAL,
+            transition: config::default::SLIDESHOW_TRANSITION,
+            order: SlideshowOrder::default(),
+            per_monitor: config::default::SLIDESHOW_PER_MONITOR,
+        }
+    }
+}
+
+impl Slideshow {
+    /// `monitor` is position of the monitor, it matters only with `per_monitor`
+    pub fn new(files: Vec<PathBuf>, params: SlideshowParams, monitor: u32) -> Self {
+        // Windows share the seed, so all monitors follow the same random sequence
+        static SEED: OnceLock<u32> = OnceLock::new();
+        let seed = *SEED.get_or_init(glib::random_int);
+        let offset = if params.per_monitor { monitor } else { 0 };
+
+        let slideshow: Self = glib::Object::builder().property("params", params).build();
+
+        let imp = slideshow.imp();
+        *imp.files.borrow_mut() = files;
+        imp.index.set(offset as usize);
+        // Xorshift state must not be zero
+        imp.state
+            .set((seed ^ offset.wrapping_mul(0x9E37_79B9)).max(1));
+        imp.start();
+
+        slideshow
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    let (content_type, _) = gio::content_type_guess(Some(path), &[]);
+    gio::content_type_is_mime_type(&content_type, "image/*")
+}
+
+fn glob(path: &Path) -> Option<&str> {
+    path.file_name()?
+        .to_str()
+        .filter(|name| name.contains(['*', '?']))
+}
+
+/// Replace leading `~` with home directory, like shell does
+fn expand_home(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => glib::home_dir().join(rest),
+        Err(_) => path.into(),
+    }
+}
+
+/// Whether background `path` is a directory or a glob pattern like `~/Pictures/*.jpg`
+pub fn is_slideshow(path: &Path) -> bool {
+    let path = expand_home(path);
+    path.is_dir() || glob(&path).is_some()
+}
+
+/// Images of a slideshow, `None` if `path` is not a slideshow
+pub fn files(path: &Path) -> Option<Vec<PathBuf>> {
+    let path = &*expand_home(path);
+    let (dir, pattern) = if path.is_dir() {
+        (path, None)
+    } else {
+        (
+            path.parent().filter(|dir| !dir.as_os_str().is_empty())?,
+            Some(glib::PatternSpec::new(glob(path)?)),
+        )
+    };
+
+    let mut files = std::fs::read_dir(dir)
+        .map_err(|err| log::warning!("failed to read {dir:?}: {err}"))
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            pattern.as_ref().is_none_or(|pattern| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| pattern.matches_string(name))
+            })
+        })
+        .filter(|path| path.is_file() && is_image(path))
+        .collect::<Vec<_>>();
+
+    files.sort();
+
+    if files.is_empty() {
+        log::warning!("no images found in {path:?}");
+    }
+
+    Some(files)
+}
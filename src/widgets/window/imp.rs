@@ -31,15 +31,32 @@ use gtk::{
     subclass::prelude::*,
 };
 
+use super::layout::{self, Layout};
 #[cfg(feature = "userinfo")]
 use crate::userinfo;
 use crate::{
+    background::{self, Background, Backgrounds},
+    cache,
     config,
     css,
+    effects::Effects,
     log,
     messages,
     pam,
-    widgets::{clock, powerbar},
+    screenshot::Screenshot,
+    state::LockState,
+    widgets::{
+        WidgetKind,
+        Widgets,
+        battery,
+        clock::{self, ClockStyle, ZoneClocks},
+        events::{self, Calendars},
+        greeting,
+        locked_since,
+        network,
+        powerbar,
+        slideshow::{self, SlideshowParams},
+    },
 };
 
 #[cfg(feature = "gpu")]
@@ -115,35 +132,6 @@ pub fn texture_to_gdk(
     gdk_tex
 }
 
-#[cfg(feature = "screenshot")]
-fn capture_monitor_screenshot(monitor: &gdk::Monitor) -> Option<blur::Image> {
-    use grim_rs::Grim;
-
-    let mut grim = Grim::new().ok()?;
-    let description = monitor.description()?;
-    let description = description.as_str();
-    let outputs = grim.get_outputs().ok()?;
-
-    for output in outputs {
-        if let Some(output_description) = output.description()
-            && description == output_description
-        {
-            let result = grim.capture_output(output.name()).ok()?;
-            let width = result.width();
-            let height = result.height();
-            let data = result.into_data();
-
-            return Some(blur::Image {
-                width,
-                height,
-                pixels: bytemuck::cast_vec(data),
-            });
-        }
-    }
-
-    None
-}
-
 // #[cfg(feature = "screenshot")]
 // #[derive(Default, Debug, Clone)]
 // struct Screenshot {
@@ -161,7 +149,6 @@ pub struct LockWindow {
     pub error_label: RefCell<Label>,
     pub error_revealer: RefCell<Revealer>,
     pub body_revealer: RefCell<Revealer>,
-    #[cfg(feature = "show-submit-button")]
     pub submit_button: RefCell<Button>,
     pub spinner: RefCell<Spinner>,
     pub busy_guard: RefCell<Option<gio::ApplicationBusyGuard>>,
@@ -174,34 +161,102 @@ pub struct LockWindow {
     pub userinfo: RefCell<userinfo::UserInfo>,
 
     pub feed: RefCell<messages::MessageWindow>,
-
-    #[cfg(feature = "screenshot")]
-    screenshot: RefCell<blur::Image>,
+    /// Kept even when not shown, it tells whether the machine is offline
+    pub network: RefCell<network::Network>,
 
     pub overlay: RefCell<Overlay>,
+    #[cfg(feature = "video")]
+    pub video: RefCell<Option<MediaFile>>,
+    /// Video played for `video_pause_after` seconds
+    pub video_expired: RefCell<bool>,
     pub screenshot_blur: RefCell<Picture>,
+    #[cfg(feature = "screenshot")]
+    pub screenshot_revealer: RefCell<Revealer>,
 
     /// Lock instance
     #[property(get, set, construct_only)]
     pub lock: RefCell<gtk_session_lock::Instance>,
+    /// State shared with windows of other monitors
+    #[property(get, set, construct_only)]
+    pub state: RefCell<LockState>,
     /// Time format
     #[property(get, set, construct, default = config::default::TIME_FORMAT)]
     pub time_format: RefCell<String>,
     /// Time format
     #[property(get, set, construct, default = config::default::DATE_FORMAT)]
     pub date_format: RefCell<String>,
+    /// Digital, analog or both clocks
+    #[property(get, set, construct, builder(ClockStyle::default()))]
+    pub clock_style: RefCell<ClockStyle>,
+    /// Show seconds hand of analog clock
+    #[property(get, set, construct, default = config::default::CLOCK_SECONDS)]
+    pub clock_seconds: RefCell<bool>,
+    /// Additional clocks for other time zones
+    #[property(get, set, construct)]
+    pub time_zones: RefCell<ZoneClocks>,
+    /// Calendars to show today's events from
+    #[property(get, set, construct)]
+    pub calendars: RefCell<Calendars>,
+    /// Greeting templates
+    #[property(get, set, construct)]
+    pub greeting: RefCell<greeting::Templates>,
+    /// Buttons of the power bar
+    #[property(get, set, construct)]
+    pub power_actions: RefCell<powerbar::PowerActions>,
     /// Whether window should appear with hidden body
     #[property(get, set, construct, default = config::default::START_HIDDEN)]
     pub start_hidden: RefCell<bool>,
     /// Idle timeout
     #[property(get, set, construct, default = config::default::IDLE_TIMEOUT)]
     pub idle_timeout: RefCell<u64>,
-    /// Path to background
+    /// Backgrounds tried in order until one loads
+    #[property(get, set, construct)]
+    pub background: RefCell<Backgrounds>,
+    /// Pause video background after this many seconds, 0 never pauses
+    #[property(get, set, construct, default = config::default::VIDEO_PAUSE_AFTER)]
+    pub video_pause_after: RefCell<u64>,
+    /// Pause video background while the form is shown
+    #[property(get, set, construct, default = config::default::VIDEO_PAUSE_ON_FORM)]
+    pub video_pause_on_form: RefCell<bool>,
+    /// Size limit of pre-rendered background cache in MiB
+    #[property(get, set, construct, default = config::default::CACHE_SIZE)]
+    pub cache_size: RefCell<u64>,
+    /// Used when background is a directory or glob
+    #[property(get, set, construct)]
+    pub slideshow: RefCell<SlideshowParams>,
+    /// Screenshot blur parameters
+    #[property(get, set, construct)]
+    pub blur: RefCell<crate::blur::BlurParams>,
+    /// Duration of progressive blur in milliseconds, 0 shows the result at once
+    #[property(get, set, construct, default = config::default::BLUR_ANIMATION)]
+    pub blur_animation: RefCell<u32>,
+    /// Number of frames of progressive blur
+    #[property(get, set, construct, minimum = 1, default = config::default::BLUR_ANIMATION_STEPS)]
+    pub blur_animation_steps: RefCell<u32>,
+    /// Screenshot effects
+    #[property(get, set, construct)]
+    pub effects: RefCell<crate::effects::Effects>,
+    /// Path to WGSL shader drawing the background
+    #[property(get, set, construct, default = None)]
+    pub shader: RefCell<Option<std::path::PathBuf>>,
+    /// Shader frame rate, 0 renders a single frame
+    #[property(get, set, construct, default = config::default::SHADER_FPS)]
+    pub shader_fps: RefCell<u32>,
+    /// Screenshot of the monitor taken before locking
+    #[property(get, set, construct_only)]
+    pub screenshot: RefCell<Option<Screenshot>>,
+    /// Optional widgets to show and their order
+    #[property(get, set, construct)]
+    pub widgets: RefCell<Widgets>,
+    /// GtkBuilder file positioning the widgets
     #[property(get, set, construct, default = None)]
-    pub background: RefCell<Option<std::path::PathBuf>>,
+    pub layout: RefCell<Option<std::path::PathBuf>>,
     /// Monitor
     #[property(get, set, construct_only)]
     pub monitor: RefCell<Option<gdk::Monitor>>,
+    /// Password accepted instead of PAM, set only in preview mode
+    #[property(get, set, construct_only)]
+    pub preview_password: RefCell<Option<String>>,
 }
 
 #[glib::object_subclass]
@@ -227,10 +282,12 @@ impl ObjectImpl for LockWindow {
 
         let error_label = Label::new(None);
 
-        #[cfg(feature = "show-submit-button")]
+        let widgets = window.widgets();
+
         let submit_button = Button::builder()
             .label("Submit")
             .css_classes(["suggested-action", "submit-button"])
+            .visible(widgets.contains(WidgetKind::SubmitButton))
             .build();
 
         let spinner = Spinner::builder().spinning(false).build();
@@ -242,14 +299,7 @@ impl ObjectImpl for LockWindow {
             .halign(Align::End)
             .build();
 
-        let main_box = Box::builder()
-            .orientation(Orientation::Vertical)
-            .spacing(8)
-            .halign(Align::Center)
-            .valign(Align::Center)
-            .hexpand(true)
-            .vexpand(true)
-            .build();
+        let layout = Layout::load(window.layout().as_deref());
 
         let body = Box::new(Orientation::Vertical, 8);
         let body_revealer = Revealer::builder().child(&body).build();
@@ -260,21 +310,11 @@ impl ObjectImpl for LockWindow {
 
         submit_row.append(&error_revealer);
         submit_row.append(&spinner);
-        #[cfg(feature = "show-submit-button")]
         submit_row.append(&submit_button);
 
-        let top_bar = Box::builder()
-            .orientation(Orientation::Horizontal)
-            .margin_top(4)
-            .margin_start(4)
-            .margin_end(4)
-            .margin_bottom(4)
-            .valign(Align::Start)
-            .build();
-
         let active_layout_label = Label::new(None);
 
-        let layout = Box::builder()
+        let keyboard_layout = Box::builder()
             .orientation(Orientation::Horizontal)
             .halign(Align::Center)
             .spacing(4)
@@ -284,34 +324,35 @@ impl ObjectImpl for LockWindow {
             .margin_bottom(4)
             .build();
 
-        layout.append(&Image::from_icon_name(
+        keyboard_layout.append(&Image::from_icon_name(
             "preferences-desktop-keyboard-symbolic",
         ));
-        layout.append(&active_layout_label);
+        keyboard_layout.append(&active_layout_label);
 
         #[cfg(feature = "playerctl")]
-        {
+        if widgets.contains(WidgetKind::Player) {
             let playerctl = crate::playerctl::PlayerControls::new();
-            top_bar.append(&playerctl);
+            layout.place("player", &playerctl);
         }
 
-        top_bar.append(&Label::builder().hexpand(true).build());
-
         let layout_frame = Frame::builder()
-            .child(&layout)
+            .child(&keyboard_layout)
             .css_classes(["bubble-frame"])
             .build();
 
         css::attach_style(".bubble-frame { background-color: #0F0F0F; }");
-        top_bar.append(&layout_frame);
+        if widgets.contains(WidgetKind::KeyboardLayout) {
+            layout.place("keyboard-layout", &layout_frame);
+        }
 
         let caps_lock_revealer = Revealer::builder()
             .child(&Label::new(Some("Caps Lock is on")))
+            .visible(widgets.contains(WidgetKind::CapsLock))
             .build();
 
-        #[cfg(feature = "show-numlock")]
         let num_lock_revealer = Revealer::builder()
             .child(&Label::new(Some("Num Lock is on")))
+            .visible(widgets.contains(WidgetKind::NumLock))
             .build();
 
         if let Some(display) = gdk::Display::default()
@@ -324,7 +365,6 @@ impl ObjectImpl for LockWindow {
                 move |keyboard| caps_lock_revealer.set_reveal_child(keyboard.is_caps_locked()),
             ));
 
-            #[cfg(feature = "show-numlock")]
             keyboard.connect_num_lock_state_notify(glib::clone!(
                 #[weak]
                 num_lock_revealer,
@@ -376,32 +416,100 @@ impl ObjectImpl for LockWindow {
         body.append(&password_entry);
         body.append(&submit_row);
         body.append(&caps_lock_revealer);
-        #[cfg(feature = "show-numlock")]
         body.append(&num_lock_revealer);
 
         #[cfg(feature = "userinfo")]
-        {
+        if widgets.contains(WidgetKind::Userinfo) {
             let userinfo = userinfo::UserInfo::new();
-            main_box.append(&userinfo);
+            layout.place("userinfo", &userinfo);
             *self.userinfo.borrow_mut() = userinfo;
         }
 
-        main_box.append(&clock::Clock::new(
-            window.time_format(),
-            window.date_format(),
-        ));
+        if widgets.contains(WidgetKind::Greeting) {
+            layout.place(
+                "greeting",
+                &greeting::Greeting::new(window.greeting(), &window.state()),
+            );
+        }
 
-        main_box.append(&body_revealer);
+        if widgets.contains(WidgetKind::Clock) {
+            let style = window.clock_style();
+
+            if style.has_analog() {
+                layout.place("clock", &clock::AnalogClock::new(window.clock_seconds()));
+            }
+            if style.has_digital() {
+                layout.place(
+                    "clock",
+                    &clock::Clock::new(
+                        window.time_format(),
+                        window.date_format(),
+                        window.time_zones(),
+                    ),
+                );
+            }
+        }
 
+        if widgets.contains(WidgetKind::Events) {
+            layout.place("events", &events::Events::new(window.calendars()));
+        }
+
+        if widgets.contains(WidgetKind::LockedSince) {
+            layout.place(
+                "locked-since",
+                &locked_since::LockedSince::new(&window.state()),
+            );
+        }
+
+        layout.place("password-form", &body_revealer);
+
+        // Feed is always there for messages, it is just not shown
         let msg = messages::MessageWindow::new();
-        main_box.append(&msg);
+        if widgets.contains(WidgetKind::Messages) {
+            layout.place("messages", &msg);
+        }
+
+        let network = network::Network::new();
+        if widgets.contains(WidgetKind::Network) {
+            layout.place("network", &network);
+        }
+
+        if widgets.contains(WidgetKind::Battery) {
+            layout.place("battery", &battery::Battery::new(&msg));
+        }
 
         let powerbar_revealer = Revealer::builder()
-            .child(&powerbar::PowerBar::new())
-            .halign(Align::Center)
-            .valign(Align::End)
-            .margin_bottom(8)
+            .child(&powerbar::PowerBar::new(window.power_actions()))
             .build();
+        if widgets.contains(WidgetKind::Powerbar) {
+            layout.place("powerbar", &powerbar_revealer);
+        }
+        layout.finish();
+
+        layout::reorder(
+            &widgets
+                .0
+                .iter()
+                .filter_map(|kind| match kind {
+                    WidgetKind::CapsLock => Some(caps_lock_revealer.clone().upcast()),
+                    WidgetKind::NumLock => Some(num_lock_revealer.clone().upcast()),
+                    WidgetKind::SubmitButton => Some(submit_button.clone().upcast()),
+                    WidgetKind::Greeting => layout.placeholder("greeting").and_upcast(),
+                    WidgetKind::Clock => layout.placeholder("clock").and_upcast(),
+                    WidgetKind::Events => layout.placeholder("events").and_upcast(),
+                    WidgetKind::LockedSince => layout.placeholder("locked-since").and_upcast(),
+                    WidgetKind::Userinfo => layout.placeholder("userinfo").and_upcast(),
+                    WidgetKind::Powerbar => layout.placeholder("powerbar").and_upcast(),
+                    WidgetKind::KeyboardLayout => {
+                        layout.placeholder("keyboard-layout").and_upcast()
+                    }
+                    WidgetKind::Battery => layout.placeholder("battery").and_upcast(),
+                    WidgetKind::Network => layout.placeholder("network").and_upcast(),
+                    WidgetKind::Player => layout.placeholder("player").and_upcast(),
+                    WidgetKind::Messages => layout.placeholder("messages").and_upcast(),
+                })
+                .collect::<Vec<_>>(),
+        );
 
         let background = Picture::builder()
             .css_name("background")
@@ -410,15 +518,25 @@ impl ObjectImpl for LockWindow {
 
         #[cfg(feature = "screenshot")]
         let screenshot_blur = Picture::builder().content_fit(ContentFit::Cover).build();
+        #[cfg(feature = "screenshot")]
+        let screenshot_revealer = Revealer::builder()
+            .transition_type(RevealerTransitionType::Crossfade)
+            .transition_duration(500)
+            .child(&screenshot_blur)
+            .reveal_child(false)
+            .build();
 
-        let background_is_screenshot = self.handle_background(&background);
+        let loaded = self.handle_background(&background);
+        let background_is_screenshot = matches!(loaded, Some(Loaded::Screenshot));
+        let slideshow = match loaded {
+            Some(Loaded::Slideshow(slideshow)) => Some(slideshow),
+            _ => None,
+        };
 
         let overlay = Overlay::new();
         #[cfg(feature = "screenshot")]
-        overlay.add_overlay(&screenshot_blur);
-        overlay.add_overlay(&main_box);
-        overlay.add_overlay(&powerbar_revealer);
-        overlay.add_overlay(&top_bar);
+        overlay.add_overlay(&screenshot_revealer);
+        overlay.add_overlay(&layout.root);
 
         let overlay_revealer = Revealer::builder()
             .transition_type(RevealerTransitionType::Crossfade)
@@ -429,6 +547,9 @@ impl ObjectImpl for LockWindow {
 
         let main_overlay = Overlay::new();
         main_overlay.add_overlay(&background);
+        if let Some(ref slideshow) = slideshow {
+            main_overlay.add_overlay(slideshow);
+        }
         main_overlay.add_overlay(&overlay_revealer);
 
         window.set_child(Some(&main_overlay));
@@ -438,13 +559,16 @@ impl ObjectImpl for LockWindow {
         *self.powerbar_revealer.borrow_mut() = powerbar_revealer;
         *self.error_label.borrow_mut() = error_label;
         *self.password_entry.borrow_mut() = password_entry;
-        #[cfg(feature = "show-submit-button")]
-        {
-            *self.submit_button.borrow_mut() = submit_button;
-        }
+        *self.submit_button.borrow_mut() = submit_button;
         *self.spinner.borrow_mut() = spinner;
         *self.active_layout_label.borrow_mut() = active_layout_label;
         *self.feed.borrow_mut() = msg;
+        *self.network.borrow_mut() = network;
+        #[cfg(feature = "screenshot")]
+        {
+            *self.screenshot_blur.borrow_mut() = screenshot_blur;
+            *self.screenshot_revealer.borrow_mut() = screenshot_revealer;
+        }
 
         self.setup_controllers(&window);
 
@@ -464,12 +588,15 @@ impl ObjectImpl for LockWindow {
 
         #[cfg(feature = "screenshot")]
         if background_is_screenshot {
-            let texture = crate::blur::do_blur(
-                &mut self.screenshot.borrow_mut(),
-                crate::blur::BlurMethod::GPU,
-            );
-            screenshot_blur.set_paintable(Some(&texture));
-            overlay_revealer.connect_child_revealed_notify(move |_| main_overlay.remove_overlay(&background));
+            self.show_screenshot(&background);
+        }
+
+        // Screenshot shader starts once effects are applied
+        if !background_is_screenshot
+            && slideshow.is_none()
+            && let Some(path) = window.shader()
+        {
+            self.start_shader(&path, &background);
         }
 
         // TODO: IDK why we need idle_add_local_once()...
@@ -593,23 +720,27 @@ impl LockWindow {
     fn authenticate(&self) {
         self.set_busy(true);
         let pwd = self.get_password();
+        let preview_password = self.preview_password.borrow().clone();
         let (tx, rx) = std::sync::mpsc::channel::<ConversationMessage>();
 
         gio::spawn_blocking(move || {
             // This runs in a thread pool, not blocking the main thread
-            let result = pam::authenticate(
-                |text: &std::ffi::OsStr| {
-                    log::warning!(
-                        "{:?}",
-                        tx.send(ConversationMessage::InfoMessage(text.into()))
-                    );
-                },
-                |text: &std::ffi::OsStr| {
-                    let _ = tx.send(ConversationMessage::ErrorMessage(text.into()));
-                },
-                String::from_utf8_lossy(glib::user_name().as_encoded_bytes()).to_string(),
-                pwd,
-            );
+            let result = match preview_password {
+                Some(expected) => pam::authenticate_static(&expected, &pwd),
+                None => pam::authenticate(
+                    |text: &std::ffi::OsStr| {
+                        log::warning!(
+                            "{:?}",
+                            tx.send(ConversationMessage::InfoMessage(text.into()))
+                        );
+                    },
+                    |text: &std::ffi::OsStr| {
+                        let _ = tx.send(ConversationMessage::ErrorMessage(text.into()));
+                    },
+                    String::from_utf8_lossy(glib::user_name().as_encoded_bytes()).to_string(),
+                    pwd,
+                ),
+            };
 
             // Send result back to main thread
             if let Err(err) = tx.send(ConversationMessage::LoginResult(result)) {
@@ -629,8 +760,11 @@ impl LockWindow {
                     match message {
                         ConversationMessage::LoginResult(result) => {
                             match result {
-                                Ok(()) => window.lock.borrow().unlock(),
-                                Err(e) => window.set_error(e),
+                                Ok(()) => window.unlock(),
+                                Err(e) => {
+                                    window.state.borrow().add_failed_attempt();
+                                    window.set_error(e);
+                                }
                             }
                             window.set_busy(false);
                             return ControlFlow::Break;
@@ -657,6 +791,15 @@ impl LockWindow {
         ));
     }
 
+    fn unlock(&self) {
+        if self.preview_password.borrow().is_some() {
+            // Session was never locked, just go away
+            self.obj().close();
+        } else {
+            self.lock.borrow().unlock();
+        }
+    }
+
     fn get_password(&self) -> String {
         let i = self.password_entry.borrow();
         let p = i.text().to_string();
@@ -665,7 +808,14 @@ impl LockWindow {
     }
 
     fn set_error(&self, error: nonstick::ErrorCode) {
-        self.error_label.borrow().set_text(&error.to_string());
+        let mut text = error.to_string();
+
+        // Network-backed accounts (SSSD, LDAP) can not be verified, the password may be right
+        if self.network.borrow().offline() {
+            text.push_str("\nThe computer is offline, network accounts can not be verified");
+        }
+
+        self.error_label.borrow().set_text(&text);
         self.error_revealer.borrow().set_reveal_child(true);
     }
 
@@ -685,7 +835,6 @@ impl LockWindow {
             move |_| callback(&window)
         ));
 
-        #[cfg(feature = "show-submit-button")]
         self.submit_button.borrow().connect_clicked(glib::clone!(
             #[weak(rename_to = window)]
             self,
@@ -713,7 +862,6 @@ impl LockWindow {
         let spinner = self.spinner.borrow();
         spinner.set_spinning(busy);
         self.password_entry.borrow().set_sensitive(!busy);
-        #[cfg(feature = "show-submit-button")]
         self.submit_button.borrow().set_sensitive(!busy);
     }
 
@@ -754,6 +902,7 @@ impl LockWindow {
         }
         //self.password_entry.borrow().grab_focus_without_selecting();
         Self::grab_focus_without_selecting(&self.password_entry.borrow());
+        self.update_video();
     }
 
     fn idle_hide(&self) {
@@ -771,6 +920,7 @@ impl LockWindow {
         }
         self.body_revealer.borrow().set_reveal_child(false);
         self.powerbar_revealer.borrow().set_reveal_child(false);
+        self.update_video();
     }
 
     fn setup_controllers(&self, window: &super::LockWindow) {
@@ -855,59 +1005,528 @@ impl LockWindow {
         window.add_controller(focus);
     }
 
-    fn handle_background(&self, background: &Picture) -> bool {
-        if let Some(path) = self.background.borrow().as_ref() {
-            if path == "screenshot" {
-                #[cfg(feature = "screenshot")]
-                if let Some(monitor) = self.monitor.borrow().as_ref() {
-                    if let Some(screenshot) = capture_monitor_screenshot(monitor) {
-                        let width = screenshot.width;
-                        let height = screenshot.height;
-
-                        background.set_paintable(Some(&gdk::MemoryTexture::new(
-                            width as i32,
-                            height as i32,
-                            gdk::MemoryFormat::R8g8b8a8,
-                            &glib::Bytes::from_owned(bytemuck::cast_vec(screenshot.pixels.clone())),
-                            (width * 4) as usize, // rowstride: bytes per row (width * 4 bytes for RGBA)
-                        )));
-
-                        *self.screenshot.borrow_mut() = screenshot;
-                        return true;
+    /// Apply effects to the screenshot on a worker thread,
+    /// sharp screenshot stays in the background until the result fades in.
+    ///
+    /// With blur animation the radius grows over several frames, if a frame takes
+    /// longer than its share of the animation we jump straight to the last one.
+    #[cfg(feature = "screenshot")]
+    fn show_screenshot(&self, background: &Picture) {
+        let revealer = self.screenshot_revealer.borrow().clone();
+        let picture = self.screenshot_blur.borrow().clone();
+
+        // Sharp screenshot is no longer needed once processed one is shown
+        revealer.connect_child_revealed_notify(glib::clone!(
+            #[weak]
+            background,
+            move |revealer| {
+                if revealer.is_child_revealed()
+                    && let Some(overlay) = background.parent().and_downcast::<Overlay>()
+                {
+                    overlay.remove_overlay(&background);
+                }
+            }
+        ));
+
+        self.process_screenshot(&revealer, &picture);
+    }
+
+    #[cfg(feature = "screenshot")]
+    fn process_screenshot(&self, revealer: &Revealer, picture: &Picture) {
+        use std::{
+            sync::mpsc::{TryRecvError, sync_channel},
+            time::{Duration, Instant},
+        };
+
+        use crate::effects::{Pipeline, Progressive};
+
+        let Some(screenshot) = self.screenshot.borrow().clone() else {
+            return;
+        };
+
+        let effects = self.effects.borrow().clone();
+        let params = *self.blur.borrow();
+        let duration = Duration::from_millis(u64::from(*self.blur_animation.borrow()));
+        let steps = if duration.is_zero() {
+            1
+        } else {
+            (*self.blur_animation_steps.borrow()).max(1)
+        };
+        let interval = duration / steps;
+
+        // Frame and, for the last one, the image for the shader.
+        // Single slot, so worker does not run far ahead of what is shown.
+        let (tx, rx) = sync_channel::<(gdk::MemoryTexture, Option<blur::Image>)>(1);
+
+        std::thread::spawn(move || {
+            // GPU context and overlay images are made once for all the frames
+            let pipeline = Pipeline::new(&effects.0, &params);
+            let steps = if pipeline.has_blur() { steps } else { 1 };
+            let mut progressive = Progressive::new(&pipeline, screenshot.to_image(), &params);
+            let mut step = 1;
+
+            while step <= steps {
+                let start = Instant::now();
+                let last = step == steps;
+
+                let image = progressive.frame((params.radius * step / steps).max(1));
+                let texture = crate::blur::to_texture(&image);
+
+                if tx.send((texture, last.then_some(image))).is_err() {
+                    return;
+                }
+
+                step = if start.elapsed() > interval {
+                    steps
+                } else {
+                    step + 1
+                };
+            }
+        });
+
+        let window = self.obj();
+
+        glib::timeout_add_local(
+            interval.max(Duration::from_millis(16)),
+            glib::clone!(
+                #[weak]
+                window,
+                #[weak]
+                revealer,
+                #[weak]
+                picture,
+                #[upgrade_or]
+                ControlFlow::Break,
+                move || {
+                    let (texture, image) = match rx.try_recv() {
+                        Ok(frame) => frame,
+                        Err(TryRecvError::Empty) => return ControlFlow::Continue,
+                        Err(TryRecvError::Disconnected) => {
+                            log::warning!("failed to process screenshot");
+                            return ControlFlow::Break;
+                        }
+                    };
+
+                    picture.set_paintable(Some(&texture));
+                    revealer.set_reveal_child(true);
+
+                    let Some(image) = image else {
+                        return ControlFlow::Continue;
+                    };
+
+                    if let Some(path) = window.shader() {
+                        #[cfg(feature = "gpu")]
+                        window.imp().run_shader(&path, Some(image), &picture);
+
+                        #[cfg(not(feature = "gpu"))]
+                        {
+                            drop(image);
+                            log::warning!("shader {path:?} is ignored, built without gpu support");
+                        }
                     }
 
-                    return false;
+                    ControlFlow::Break
                 }
+            ),
+        );
+    }
+
+    /// Run shader over whatever `picture` currently shows
+    fn start_shader(&self, path: &std::path::Path, picture: &Picture) {
+        #[cfg(feature = "gpu")]
+        {
+            let input = picture
+                .paintable()
+                .and_downcast::<gdk::Texture>()
+                .map(|texture| crate::blur::from_texture(&texture));
+
+            self.run_shader(path, input, picture);
+        }
+
+        #[cfg(not(feature = "gpu"))]
+        {
+            let _ = picture;
+            log::warning!("shader {path:?} is ignored, built without gpu support");
+        }
+    }
+
+    /// Position of our monitor in display's monitor list
+    fn monitor_index(&self) -> u32 {
+        let monitor = self.monitor.borrow();
+
+        gdk::Display::default()
+            .map(|display| display.monitors())
+            .and_then(|monitors| {
+                (0..monitors.n_items())
+                    .find(|&i| monitors.item(i).and_downcast::<gdk::Monitor>() == *monitor)
+            })
+            .unwrap_or(0)
+    }
+
+    /// Render shader frames on a worker thread and show them in `picture`
+    #[cfg(feature = "gpu")]
+    fn run_shader(&self, path: &std::path::Path, input: Option<blur::Image>, picture: &Picture) {
+        use std::{
+            sync::mpsc::{TryRecvError, channel},
+            time::{Duration, Instant},
+        };
 
-                #[cfg(not(feature = "screenshot"))]
-                log::warning!("screenshot background is not supported");
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                log::warning!("failed to read shader {path:?}: {err}");
+                return;
+            }
+        };
 
-                return false;
+        let (width, height) = match (&input, self.monitor.borrow().as_ref()) {
+            (Some(image), _) => (image.width, image.height),
+            (None, Some(monitor)) => {
+                let geometry = monitor.geometry();
+                let scale = monitor.scale();
+                (
+                    (f64::from(geometry.width()) * scale) as u32,
+                    (f64::from(geometry.height()) * scale) as u32,
+                )
             }
+            (None, None) => (1, 1),
+        };
 
-            #[cfg(feature = "video")]
-            infer::get_from_path(path)
-                .map_err(|err| log::warning!("failed to load background {path:?}: {err}"))
-                .ok()
-                .flatten()
-                .map(|kind| match kind.matcher_type() {
-                    infer::MatcherType::Video => {
-                        let video = MediaFile::for_filename(path);
-
-                        video.set_loop(true);
-                        video.set_muted(true);
-                        video.play();
-
-                        background.set_paintable(Some(&video));
+        let monitor = self.monitor_index();
+        let fps = *self.shader_fps.borrow();
+        let (request_tx, request_rx) = channel::<f32>();
+        let (frame_tx, frame_rx) = channel::<blur::Image>();
+
+        std::thread::spawn(move || {
+            let shader =
+                match crate::shader::Shader::new(&source, input.as_ref(), width, height, monitor) {
+                    Ok(shader) => shader,
+                    Err(err) => {
+                        log::warning!("failed to create shader: {err}");
+                        return;
                     }
-                    infer::MatcherType::Image => background.set_filename(Some(&path)),
-                    _ => log::warning!("unsupported type {:?}", kind.matcher_type()),
-                });
+                };
 
-            #[cfg(not(feature = "video"))]
-            background.set_filename(Some(&path));
+            // Thread ends once window drops the sender
+            while let Ok(time) = request_rx.recv() {
+                // Render only the most recent request if we lag behind
+                let time = request_rx.try_iter().last().unwrap_or(time);
+
+                match shader.render(time) {
+                    Some(frame) if frame_tx.send(frame).is_ok() => {}
+                    _ => break,
+                }
+            }
+        });
+
+        let start = Instant::now();
+        let _ = request_tx.send(0.0);
+
+        let interval = if fps > 0 {
+            Duration::from_secs(1) / fps
+        } else {
+            Duration::from_millis(16)
+        };
+
+        glib::timeout_add_local(
+            interval,
+            glib::clone!(
+                #[weak]
+                picture,
+                #[upgrade_or]
+                ControlFlow::Break,
+                move || match frame_rx.try_recv() {
+                    Ok(frame) => {
+                        picture.set_paintable(Some(&crate::blur::to_texture(&frame)));
+
+                        if fps == 0 || request_tx.send(start.elapsed().as_secs_f32()).is_err() {
+                            return ControlFlow::Break;
+                        }
+
+                        ControlFlow::Continue
+                    }
+                    Err(TryRecvError::Empty) => ControlFlow::Continue,
+                    Err(TryRecvError::Disconnected) => ControlFlow::Break,
+                }
+            ),
+        );
+    }
+
+    /// Decoder failed, try `fallbacks`, then the last frame if there is one
+    #[cfg(feature = "video")]
+    fn video_failed(&self, video: &MediaFile, picture: &Picture, fallbacks: &[Background]) {
+        let Some(err) = video.error() else {
+            return;
+        };
+
+        log::warning!("video failed: {err}");
+        *self.video.borrow_mut() = None;
+
+        if self.load_fallback(picture, fallbacks) {
+            return;
+        }
+
+        if video.is_prepared() && video.timestamp() > 0 {
+            log::info!("showing last frame of the video");
+            picture.set_paintable(Some(&video.current_image()));
+            return;
+        }
+
+        let black = Background::Color {
+            color: "black".into(),
+        };
+        let (width, height) = self.logical_size();
+        picture.set_paintable(background::paint(&black, width, height).ok().as_ref());
+    }
+
+    /// Load the first of `fallbacks` that works in place of a background that failed
+    /// after construction, false if none does
+    fn load_fallback(&self, picture: &Picture, fallbacks: &[Background]) -> bool {
+        for (i, background) in fallbacks.iter().enumerate() {
+            match self.load_background(background, picture, &fallbacks[i + 1..]) {
+                Ok(Loaded::Picture) => return true,
+                Ok(Loaded::Screenshot) => {
+                    #[cfg(feature = "screenshot")]
+                    self.show_screenshot(picture);
+                    return true;
+                }
+                Ok(Loaded::Slideshow(slideshow)) => {
+                    // Same place in the stack as when loaded at construction
+                    picture.set_paintable(gdk::Paintable::NONE);
+                    if let Some(overlay) = picture.parent() {
+                        slideshow.insert_after(&overlay, Some(picture));
+                    }
+                    return true;
+                }
+                Err(err) => log::warning!("failed to load background {background:?}: {err}"),
+            }
         }
 
         false
     }
+
+    /// Play or pause video background according to `video_pause_*`
+    fn update_video(&self) {
+        #[cfg(feature = "video")]
+        if let Some(video) = self.video.borrow().as_ref() {
+            let form_shown = !self.obj().has_css_class("hidden");
+            let paused =
+                *self.video_expired.borrow() || (*self.video_pause_on_form.borrow() && form_shown);
+
+            video.set_playing(!paused);
+        }
+    }
+
+    /// Image with effects fitted to our monitor, from cache if it was rendered before.
+    /// Rendering finishes later, if it fails `fallbacks` are tried then.
+    fn render_cached(
+        &self,
+        path: &std::path::Path,
+        fit: background::Fit,
+        effects: &[crate::effects::Effect],
+        picture: &Picture,
+        fallbacks: &[Background],
+    ) {
+        let (width, height) = match self.monitor.borrow().as_ref() {
+            Some(monitor) => cache::monitor_size(monitor),
+            None => {
+                let (width, height) = self.logical_size();
+                (width as u32, height as u32)
+            }
+        };
+
+        let path = path.to_owned();
+        let effects = effects.to_vec();
+        let params = *self.blur.borrow();
+        let cache_size = *self.cache_size.borrow();
+        let fallbacks = fallbacks.to_vec();
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            picture,
+            async move {
+                let result = gio::spawn_blocking(move || {
+                    cache::Cache::new(cache_size)?
+                        .render(&path, width, height, fit, &effects, &params)
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(texture)) => {
+                        picture.set_paintable(Some(&texture));
+                        return;
+                    }
+                    Ok(Err(err)) => log::warning!("failed to render background: {err}"),
+                    Err(_) => log::warning!("failed to render background"),
+                }
+
+                if !window.load_fallback(&picture, &fallbacks) {
+                    log::warning!("no fallback background left");
+                }
+            }
+        ));
+    }
+
+    /// Size of the monitor in logical pixels
+    fn logical_size(&self) -> (f32, f32) {
+        self.monitor
+            .borrow()
+            .as_ref()
+            .map(|monitor| {
+                let geometry = monitor.geometry();
+                (geometry.width() as f32, geometry.height() as f32)
+            })
+            .unwrap_or((1920.0, 1080.0))
+    }
+
+    /// Load the first background of our monitor's chain that works
+    fn handle_background(&self, picture: &Picture) -> Option<Loaded> {
+        let backgrounds = self.background.borrow().clone();
+        let monitor = self.monitor.borrow().clone();
+
+        let chain = backgrounds.get(monitor.as_ref());
+
+        for (i, background) in chain.iter().enumerate() {
+            match self.load_background(background, picture, &chain[i + 1..]) {
+                Ok(loaded) => return Some(loaded),
+                Err(err) => log::warning!("failed to load background {background:?}: {err}"),
+            }
+        }
+
+        None
+    }
+
+    fn load_background(
+        &self,
+        background: &Background,
+        picture: &Picture,
+        fallbacks: &[Background],
+    ) -> Result<Loaded, background::Error> {
+        match background {
+            Background::Color { .. } | Background::Linear { .. } | Background::Radial { .. } => {
+                let (width, height) = self.logical_size();
+                let paintable = background::paint(background, width, height)?;
+
+                picture.set_content_fit(ContentFit::Fill);
+                picture.set_paintable(Some(&paintable));
+            }
+            Background::Image {
+                path,
+                fit,
+                effects: Some(effects),
+            } => {
+                if !path.exists() {
+                    return Err(background::Error::NotFound(path.clone()));
+                }
+
+                picture.set_content_fit((*fit).into());
+                self.render_cached(path, *fit, effects, picture, fallbacks);
+            }
+            Background::Image { path, fit, .. } => {
+                let texture = gdk::Texture::from_filename(path)
+                    .map_err(|err| background::Error::Load(path.clone(), err))?;
+
+                picture.set_content_fit((*fit).into());
+                picture.set_paintable(Some(&texture));
+            }
+            #[cfg(feature = "video")]
+            Background::Video {
+                path,
+                looped,
+                muted,
+            } => {
+                if !path.exists() {
+                    return Err(background::Error::NotFound(path.clone()));
+                }
+
+                let video = MediaFile::for_filename(path);
+
+                video.set_loop(*looped);
+                video.set_muted(*muted);
+                video.connect_error_notify(glib::clone!(
+                    #[weak(rename_to = window)]
+                    self,
+                    #[weak]
+                    picture,
+                    #[strong(rename_to = fallbacks)]
+                    fallbacks.to_vec(),
+                    move |video| window.video_failed(video, &picture, &fallbacks)
+                ));
+                video.play();
+
+                picture.set_paintable(Some(&video));
+                *self.video.borrow_mut() = Some(video);
+
+                let pause_after = *self.video_pause_after.borrow();
+                if pause_after > 0 {
+                    glib::timeout_add_seconds_local_once(
+                        pause_after as u32,
+                        glib::clone!(
+                            #[weak(rename_to = window)]
+                            self,
+                            move || {
+                                *window.video_expired.borrow_mut() = true;
+                                window.update_video();
+                            }
+                        ),
+                    );
+                }
+
+                // Form visibility is known once the window is set up
+                glib::idle_add_local_once(glib::clone!(
+                    #[weak(rename_to = window)]
+                    self,
+                    move || window.update_video()
+                ));
+            }
+            #[cfg(not(feature = "video"))]
+            Background::Video { .. } => {
+                let _ = fallbacks;
+                return Err(background::Error::Unsupported("video"));
+            }
+            Background::Slideshow { path } => {
+                let files = slideshow::files(path).unwrap_or_default();
+                if files.is_empty() {
+                    return Err(background::Error::EmptySlideshow(path.clone()));
+                }
+
+                return Ok(Loaded::Slideshow(slideshow::Slideshow::new(
+                    files,
+                    *self.slideshow.borrow(),
+                    self.monitor_index(),
+                )));
+            }
+            #[cfg(feature = "screenshot")]
+            Background::Screenshot { effects } => {
+                // Shown as is until effects are applied
+                let screenshot = self.screenshot.borrow();
+                let screenshot = screenshot.as_ref().ok_or(background::Error::NoScreenshot)?;
+
+                picture.set_paintable(Some(&screenshot.texture));
+
+                if let Some(effects) = effects {
+                    *self.effects.borrow_mut() = Effects(effects.clone());
+                }
+
+                return Ok(Loaded::Screenshot);
+            }
+            #[cfg(not(feature = "screenshot"))]
+            Background::Screenshot { .. } => {
+                return Err(background::Error::Unsupported("screenshot"));
+            }
+        }
+
+        Ok(Loaded::Picture)
+    }
+}
+
+/// What ended up drawn as background
+enum Loaded {
+    /// Anything shown by the background picture itself
+    Picture,
+    /// Screenshot that is yet to be processed
+    Screenshot,
+    Slideshow(slideshow::Slideshow),
 }
@@ -0,0 +1,156 @@
+use std::path::Path;
+
+use gtk::{Box, Builder, Widget, glib, prelude::*};
+
+use crate::log;
+
+const DEFAULT: &str = include_str!("layout.ui");
+
+/// Ids of empty `GtkBox` objects our widgets are put into
+const PLACEHOLDERS: [&str; 12] = [
+    "greeting",
+    "clock",
+    "events",
+    "locked-since",
+    "userinfo",
+    "password-form",
+    "powerbar",
+    "keyboard-layout",
+    "battery",
+    "network",
+    "messages",
+    "player",
+];
+
+/// Without these the screen can not be unlocked
+const REQUIRED: [&str; 1] = ["password-form"];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Load(#[from] glib::Error),
+
+    #[error("no widget with id \"layout\"")]
+    NoRoot,
+
+    #[error("required placeholder \"{0}\" is missing")]
+    Missing(&'static str),
+
+    #[error("placeholder \"{0}\" must be a GtkBox")]
+    NotBox(&'static str),
+}
+
+/// Widget tree from a GtkBuilder file
+pub struct Layout {
+    pub root: Widget,
+    builder: Builder,
+}
+
+impl Layout {
+    /// Load user layout, falling back to the built-in one
+    pub fn load(path: Option<&Path>) -> Self {
+        if let Some(path) = path {
+            let builder = Builder::new();
+
+            match builder
+                .add_from_file(path)
+                .map_err(Error::from)
+                .and_then(|()| Self::new(builder))
+            {
+                Ok(layout) => return layout,
+                Err(err) => log::error!("invalid layout {path:?}, using default: {err}"),
+            }
+        }
+
+        Self::new(Builder::from_string(DEFAULT)).expect("built-in layout is valid")
+    }
+
+    fn new(builder: Builder) -> Result<Self, Error> {
+        let root = builder.object::<Widget>("layout").ok_or(Error::NoRoot)?;
+
+        for name in PLACEHOLDERS {
+            if builder.object::<glib::Object>(name).is_some()
+                && builder.object::<Box>(name).is_none()
+            {
+                return Err(Error::NotBox(name));
+            }
+        }
+
+        if let Some(name) = REQUIRED
+            .into_iter()
+            .find(|name| builder.object::<Box>(name).is_none())
+        {
+            return Err(Error::Missing(name));
+        }
+
+        Ok(Self { root, builder })
+    }
+
+    /// Put `widget` into placeholder `name`, it is not shown if layout has no such placeholder
+    pub fn place(&self, name: &'static str, widget: &impl IsA<Widget>) {
+        debug_assert!(PLACEHOLDERS.contains(&name));
+
+        if let Some(placeholder) = self.builder.object::<Box>(name) {
+            placeholder.append(widget);
+        }
+    }
+
+    pub fn placeholder(&self, name: &'static str) -> Option<Box> {
+        self.builder.object::<Box>(name)
+    }
+
+    /// Hide placeholders left empty, e.g. of disabled features, so they take no spacing
+    pub fn finish(&self) {
+        for name in PLACEHOLDERS {
+            if let Some(placeholder) = self.builder.object::<Box>(name)
+                && placeholder.first_child().is_none()
+            {
+                placeholder.set_visible(false);
+            }
+        }
+    }
+}
+
+/// Swap two children of `parent`, `a` goes before `b`
+fn swap(parent: &Box, a: &Widget, b: &Widget) {
+    let before_a = a.prev_sibling();
+    let before_b = b.prev_sibling();
+
+    if before_b.as_ref() == Some(a) {
+        parent.reorder_child_after(a, Some(b));
+    } else {
+        parent.reorder_child_after(b, before_a.as_ref());
+        parent.reorder_child_after(a, before_b.as_ref());
+    }
+}
+
+/// Order `widgets` sharing a parent `GtkBox` as given. They swap places with each other,
+/// so other children like spacers stay where they are.
+pub fn reorder(widgets: &[Widget]) {
+    let mut groups = Vec::<(Box, Vec<&Widget>)>::new();
+
+    for widget in widgets {
+        let Some(parent) = widget.parent().and_downcast::<Box>() else {
+            continue;
+        };
+
+        match groups.iter_mut().find(|(other, _)| *other == parent) {
+            Some((_, group)) => group.push(widget),
+            None => groups.push((parent, vec![widget])),
+        }
+    }
+
+    for (parent, wanted) in groups {
+        for (index, widget) in wanted.iter().enumerate() {
+            let current = std::iter::successors(parent.first_child(), Widget::next_sibling)
+                .filter(|child| wanted.iter().any(|widget| *widget == child))
+                .nth(index);
+
+            if let Some(current) = current
+                && current != **widget
+            {
+                swap(&parent, &current, widget);
+            }
+        }
+    }
+}
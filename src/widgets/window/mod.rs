@@ -1,4 +1,5 @@
 mod imp;
+mod layout;
 
 use gtk::{
     Accessible,
@@ -16,6 +17,23 @@ use gtk::{
     glib::{self, object::IsA},
 };
 
+use crate::{
+    background::Backgrounds,
+    blur::BlurParams,
+    config::Config,
+    effects::Effects,
+    screenshot::Screenshot,
+    state::LockState,
+    widgets::{
+        Widgets,
+        clock::{ClockStyle, ZoneClocks},
+        events::Calendars,
+        greeting::Templates,
+        powerbar::PowerActions,
+        slideshow::SlideshowParams,
+    },
+};
+
 glib::wrapper! {
     pub struct LockWindow(ObjectSubclass<imp::LockWindow>)
         @extends Widget, Window, ApplicationWindow,
@@ -63,6 +81,12 @@ impl LockWindowBuilder {
         }
     }
 
+    pub fn state(self, state: &LockState) -> Self {
+        Self {
+            builder: self.builder.property("state", state),
+        }
+    }
+
     pub fn time_format(self, format: &str) -> Self {
         Self {
             builder: self.builder.property("time-format", format),
@@ -75,6 +99,39 @@ impl LockWindowBuilder {
         }
     }
 
+    pub fn clock(self, style: ClockStyle, seconds: bool) -> Self {
+        Self {
+            builder: self
+                .builder
+                .property("clock-style", style)
+                .property("clock-seconds", seconds),
+        }
+    }
+
+    pub fn time_zones(self, zones: ZoneClocks) -> Self {
+        Self {
+            builder: self.builder.property("time-zones", zones),
+        }
+    }
+
+    pub fn calendars(self, calendars: Calendars) -> Self {
+        Self {
+            builder: self.builder.property("calendars", calendars),
+        }
+    }
+
+    pub fn greeting(self, templates: Templates) -> Self {
+        Self {
+            builder: self.builder.property("greeting", templates),
+        }
+    }
+
+    pub fn power_actions(self, actions: PowerActions) -> Self {
+        Self {
+            builder: self.builder.property("power-actions", actions),
+        }
+    }
+
     pub fn idle_timeout(self, timeout: u64) -> Self {
         Self {
             builder: self.builder.property("idle-timeout", timeout),
@@ -87,21 +144,131 @@ impl LockWindowBuilder {
         }
     }
 
-    pub fn background(self, background: Option<impl AsRef<std::path::Path>>) -> Self {
-        if let Some(background) = background {
+    pub fn background(self, backgrounds: Backgrounds) -> Self {
+        Self {
+            builder: self.builder.property("background", backgrounds),
+        }
+    }
+
+    pub fn monitor(self, monitor: &gdk::Monitor) -> Self {
+        Self {
+            builder: self.builder.property("monitor", monitor),
+        }
+    }
+
+    pub fn blur(self, blur: BlurParams) -> Self {
+        Self {
+            builder: self.builder.property("blur", blur),
+        }
+    }
+
+    pub fn blur_animation(self, duration: u32, steps: u32) -> Self {
+        Self {
+            builder: self
+                .builder
+                .property("blur-animation", duration)
+                .property("blur-animation-steps", steps),
+        }
+    }
+
+    pub fn slideshow(self, slideshow: SlideshowParams) -> Self {
+        Self {
+            builder: self.builder.property("slideshow", slideshow),
+        }
+    }
+
+    pub fn video_pause(self, after: u64, on_form: bool) -> Self {
+        Self {
+            builder: self
+                .builder
+                .property("video-pause-after", after)
+                .property("video-pause-on-form", on_form),
+        }
+    }
+
+    pub fn cache_size(self, size: u64) -> Self {
+        Self {
+            builder: self.builder.property("cache-size", size),
+        }
+    }
+
+    pub fn effects(self, effects: Effects) -> Self {
+        Self {
+            builder: self.builder.property("effects", effects),
+        }
+    }
+
+    pub fn widgets(self, widgets: Widgets) -> Self {
+        Self {
+            builder: self.builder.property("widgets", widgets),
+        }
+    }
+
+    pub fn shader(self, shader: Option<impl AsRef<std::path::Path>>) -> Self {
+        if let Some(shader) = shader {
             Self {
-                builder: self
-                    .builder
-                    .property("background", background.as_ref().to_str()),
+                builder: self.builder.property("shader", shader.as_ref().to_str()),
             }
         } else {
             self
         }
     }
 
-    pub fn monitor(self, monitor: &gdk::Monitor) -> Self {
+    pub fn layout(self, layout: Option<impl AsRef<std::path::Path>>) -> Self {
+        if let Some(layout) = layout {
+            Self {
+                builder: self.builder.property("layout", layout.as_ref().to_str()),
+            }
+        } else {
+            self
+        }
+    }
+
+    pub fn shader_fps(self, fps: u32) -> Self {
         Self {
-            builder: self.builder.property("monitor", monitor),
+            builder: self.builder.property("shader-fps", fps),
         }
     }
+
+    pub fn screenshot(self, screenshot: Option<Screenshot>) -> Self {
+        Self {
+            builder: self.builder.property("screenshot", screenshot),
+        }
+    }
+
+    pub fn preview_password(self, password: &str) -> Self {
+        Self {
+            builder: self.builder.property("preview-password", password),
+        }
+    }
+
+    /// Set all properties that come from the configuration
+    pub fn config(self, config: &Config) -> Self {
+        self.start_hidden(config.get_start_hidden())
+            .idle_timeout(config.get_idle_timeout())
+            .time_format(config.get_time_format())
+            .date_format(config.get_date_format())
+            .clock(config.get_clock_style(), config.get_clock_seconds())
+            .time_zones(config.get_time_zones())
+            .calendars(config.get_calendars())
+            .greeting(config.get_greeting())
+            .power_actions(config.get_power_actions())
+            .background(config.get_backgrounds())
+            .blur(config.get_blur())
+            .blur_animation(
+                config.get_blur_animation(),
+                config.get_blur_animation_steps(),
+            )
+            .slideshow(config.get_slideshow())
+            .video_pause(
+                config.get_video_pause_after(),
+                config.get_video_pause_on_form(),
+            )
+            .cache_size(config.get_cache_size())
+            .effects(config.get_effects())
+            .shader(config.get_shader())
+            .shader_fps(config.get_shader_fps())
+            .layout(config.get_layout())
+            .widgets(config.get_widgets())
+    }
 }